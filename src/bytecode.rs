@@ -0,0 +1,189 @@
+//! # Ether Deck Bytecode Module
+//!
+//! Contains functions for verifying that an address holds the deck's runtime code.
+//!
+//! The constructor copies the runtime code out of the init code with
+//! `PUSH2 length DUP1 PUSH1 offset RETURNDATASIZE CODECOPY RETURNDATASIZE RETURN`.
+use crate::{deploy::ETHER_DECK_BYTECODE, error::Error};
+use ethers::{prelude::*, utils::keccak256};
+
+const PUSH1: u8 = 0x60;
+const PUSH32: u8 = 0x7f;
+const DUP1: u8 = 0x80;
+const RETURNDATASIZE: u8 = 0x3d;
+const CODECOPY: u8 = 0x39;
+
+/// ## Code Verdict Enum
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    /// The code is the runtime code of [ETHER_DECK_BYTECODE].
+    Exact,
+    /// The code hashes to one of the known runtime code hashes.
+    KnownVersion(H256),
+    /// The code is not recognized.
+    Unknown,
+    /// The address has no code.
+    NoCode,
+}
+
+/// ## Runtime Bytecode
+///
+/// ### Parameters
+///
+/// - `init_code`: The init code.
+///
+/// ### Returns
+///
+/// Either the runtime code the constructor copies, or
+/// [MalformedBytecode](crate::error::Error::MalformedBytecode) if the constructor does not match
+/// the deck's constructor layout.
+pub fn runtime_bytecode(init_code: &[u8]) -> Result<&[u8], Error> {
+    let mut instructions: Vec<(u8, &[u8])> = Vec::new();
+    let mut pc = 0;
+
+    while pc < init_code.len() {
+        let opcode = init_code[pc];
+        let size = match opcode {
+            PUSH1..=PUSH32 => (opcode - PUSH1 + 1) as usize,
+            _ => 0,
+        };
+        let immediate = init_code
+            .get(pc + 1..pc + 1 + size)
+            .ok_or(Error::MalformedBytecode)?;
+
+        if opcode == CODECOPY {
+            let (length, offset) = match instructions.as_slice() {
+                [.., length, (DUP1, _), offset, (RETURNDATASIZE, _)] => {
+                    (push_value(length)?, push_value(offset)?)
+                }
+                _ => return Err(Error::MalformedBytecode),
+            };
+            return offset
+                .checked_add(length)
+                .and_then(|end| init_code.get(offset..end))
+                .ok_or(Error::MalformedBytecode);
+        }
+
+        instructions.push((opcode, immediate));
+        pc += 1 + size;
+    }
+
+    Err(Error::MalformedBytecode)
+}
+
+/// ## Classify Code
+///
+/// ### Parameters
+///
+/// - `code`: The runtime code found at an address.
+/// - `known`: Runtime code hashes of other deck versions.
+///
+/// ### Returns
+///
+/// The [Verdict].
+pub fn classify_code(code: &[u8], known: &[H256]) -> Result<Verdict, Error> {
+    if code.is_empty() {
+        return Ok(Verdict::NoCode);
+    }
+    if code == runtime_bytecode(&ETHER_DECK_BYTECODE)? {
+        return Ok(Verdict::Exact);
+    }
+
+    let hash = H256(keccak256(code));
+    match known.contains(&hash) {
+        true => Ok(Verdict::KnownVersion(hash)),
+        false => Ok(Verdict::Unknown),
+    }
+}
+
+/// ## Verify Code
+///
+/// ### Parameters
+///
+/// - `provider`: The provider to read the code with.
+/// - `address`: The address to verify.
+/// - `known`: Runtime code hashes of other deck versions.
+/// - `block`: The block to read the code at, `latest` if `None`.
+///
+/// ### Returns
+///
+/// Either the [Verdict] for the code at the block or an [Error](crate::error::Error).
+pub async fn verify_code<M>(
+    provider: M,
+    address: &Address,
+    known: &[H256],
+    block: Option<BlockId>,
+) -> Result<Verdict, Error>
+where
+    M: Middleware + 'static,
+{
+    let code = provider
+        .get_code(*address, block)
+        .await
+        .map_err(|e| Error::Middleware(e.to_string()))?;
+
+    classify_code(&code, known)
+}
+
+/// Reads a push instruction's immediate as an offset or length.
+fn push_value((opcode, immediate): &(u8, &[u8])) -> Result<usize, Error> {
+    match (opcode, immediate.len()) {
+        (PUSH1..=PUSH32, 1..=8) => {
+            Ok(immediate.iter().fold(0, |acc, byte| (acc << 8) | *byte as usize))
+        }
+        _ => Err(Error::MalformedBytecode),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_runtime_bytecode() {
+        let runtime = runtime_bytecode(&ETHER_DECK_BYTECODE).unwrap();
+
+        assert_eq!(runtime.len(), 0x033d);
+        assert_eq!(runtime, &ETHER_DECK_BYTECODE[0x1f..]);
+        // the dispatcher loads the selector first
+        assert_eq!(&runtime[..5], &[0x60, 0x00, 0x35, 0x60, 0xe0]);
+    }
+
+    #[test]
+    fn test_runtime_bytecode_malformed() {
+        assert_eq!(runtime_bytecode(&[]), Err(Error::MalformedBytecode));
+        // CODECOPY without the constructor's pushes
+        assert_eq!(runtime_bytecode(&[0x3d, 0x3d, 0x3d, 0x39]), Err(Error::MalformedBytecode));
+        // truncated push
+        assert_eq!(runtime_bytecode(&[0x61, 0x03]), Err(Error::MalformedBytecode));
+        // copy past the end of the init code
+        let init = [0x61, 0x01, 0x00, 0x80, 0x60, 0x0a, 0x3d, 0x39, 0x3d, 0xf3];
+        assert_eq!(runtime_bytecode(&init), Err(Error::MalformedBytecode));
+    }
+
+    #[test]
+    fn test_classify_code() {
+        let runtime = runtime_bytecode(&ETHER_DECK_BYTECODE).unwrap();
+        let other = [0x00];
+        let known = [H256(keccak256(other))];
+
+        assert_eq!(classify_code(runtime, &[]), Ok(Verdict::Exact));
+        assert_eq!(classify_code(&other, &known), Ok(Verdict::KnownVersion(known[0])));
+        assert_eq!(classify_code(&other, &[]), Ok(Verdict::Unknown));
+        assert_eq!(classify_code(&[], &known), Ok(Verdict::NoCode));
+    }
+
+    #[tokio::test]
+    async fn test_verify_code() {
+        let runtime = runtime_bytecode(&ETHER_DECK_BYTECODE).unwrap().to_vec();
+        let (provider, mock) = Provider::mocked();
+        mock.push::<Bytes, _>(Bytes::from(runtime)).unwrap();
+        let block = BlockId::from(U64::from(10));
+
+        assert_eq!(
+            verify_code(provider, &Address::zero(), &[], Some(block)).await,
+            Ok(Verdict::Exact)
+        );
+        mock.assert_request("eth_getCode", (Address::zero(), block)).unwrap();
+    }
+}
@@ -2,14 +2,22 @@
 //!
 //! Contains functions for decoding each of the Ether Deck contract's methods.
 use crate::{
-    coder::structures::{Encoding, SetAuth, SetShard, SetThreshold, Syscall},
-    constants::{SELECTOR_SET_AUTH, SELECTOR_SET_SHARD, SELECTOR_SET_THRESHOLD, SELECTOR_SYSCALL},
+    coder::structures::{DeckSignature, Encoding, SetAuth, SetShard, SetThreshold, Syscall},
+    constants::{
+        SELECTOR_SET_AUTH, SELECTOR_SET_SHARD, SELECTOR_SET_THRESHOLD, SELECTOR_SYSCALL,
+        SYSCALL_DEADLINE_OFFSET, SYSCALL_ID_OFFSET, SYSCALL_PAYLOAD_LEN_OFFSET,
+        SYSCALL_PAYLOAD_OFFSET, SYSCALL_SIGNATURE_LEN, SYSCALL_TARGET_OFFSET, SYSCALL_VALUE_OFFSET,
+    },
     error::Error,
 };
-use ethers::prelude::{Address, Signature, U256, U64};
+use ethers::prelude::{Address, U256, U64};
 
 /// ## Decode Calldata
 ///
+/// Calldata with a non-reserved selector decodes to
+/// [ShardCall](crate::coder::structures::Encoding::ShardCall), as the deck's fallback delegates
+/// it to the selector's shard.
+///
 /// ### Parameters
 ///
 /// - `calldata`: The calldata to decode.
@@ -19,12 +27,21 @@ use ethers::prelude::{Address, Signature, U256, U64};
 /// Either an [Encoding](crate::coder::structures::Encoding) field or an
 /// [Error](crate::error::Error).
 pub fn decode(calldata: &[u8]) -> Result<Encoding, Error> {
-    let encoding = match calldata.try_into().map_err(|_| Error::CalldataLength)? {
+    let selector: [u8; 4] = calldata
+        .get(..4)
+        .ok_or(Error::CalldataLength)?
+        .try_into()
+        .map_err(|_| Error::CalldataLength)?;
+
+    let encoding = match selector {
         SELECTOR_SET_AUTH => Encoding::SetAuth(decode_set_auth(calldata)?),
         SELECTOR_SET_THRESHOLD => Encoding::SetThreshold(decode_set_threshold(calldata)?),
         SELECTOR_SET_SHARD => Encoding::SetShard(decode_set_shard(calldata)?),
         SELECTOR_SYSCALL => Encoding::Syscall(decode_syscall(calldata)?),
-        _ => return Err(Error::SelectorMismatch),
+        _ => Encoding::ShardCall {
+            selector,
+            data: calldata[4..].to_vec(),
+        },
     };
     Ok(encoding)
 }
@@ -78,7 +95,7 @@ pub fn decode_set_threshold(calldata: &[u8]) -> Result<SetThreshold, Error> {
 /// Either a [SetShard](crate::coder::structures::SetShard) structure or an
 /// [Error](crate::error::Error).
 pub fn decode_set_shard(calldata: &[u8]) -> Result<SetShard, Error> {
-    if calldata.len() != 29 {
+    if calldata.len() != 28 {
         return Err(Error::CalldataLength);
     }
 
@@ -103,42 +120,252 @@ pub fn decode_set_shard(calldata: &[u8]) -> Result<SetShard, Error> {
 /// Either a [Syscall](crate::coder::structures::Syscall) structure or an
 /// [Error](crate::error::Error).
 pub fn decode_syscall(calldata: &[u8]) -> Result<Syscall, Error> {
-    if calldata.len() < 100 {
+    if calldata.len() < SYSCALL_PAYLOAD_OFFSET {
         return Err(Error::CalldataLength);
     }
 
-    let payload_len = u32::from_be_bytes(calldata[96..100].try_into().unwrap()) as usize;
+    let payload_len = u32::from_be_bytes(
+        calldata[SYSCALL_PAYLOAD_LEN_OFFSET..SYSCALL_PAYLOAD_OFFSET]
+            .try_into()
+            .map_err(|_| Error::CalldataLength)?,
+    ) as usize;
+    let payload_end = SYSCALL_PAYLOAD_OFFSET + payload_len;
 
-    if calldata.len() != 100 + payload_len {
+    if calldata.len() < payload_end {
         return Err(Error::CalldataLength);
     }
 
-    let id = U256::from_big_endian(&calldata[4..36]);
-    let target = Address::from_slice(&calldata[36..56]);
-    let value = U256::from_big_endian(&calldata[56..67]); // TODO: verify this does not panic.
-    let deadline = U64::from_big_endian(&calldata[67..75]);
-    let payload = &calldata[79..(79 + payload_len)];
+    let id = U256::from_big_endian(&calldata[SYSCALL_ID_OFFSET..SYSCALL_TARGET_OFFSET]);
+    let target = Address::from_slice(&calldata[SYSCALL_TARGET_OFFSET..SYSCALL_VALUE_OFFSET]);
+    let value = U256::from_big_endian(&calldata[SYSCALL_VALUE_OFFSET..SYSCALL_DEADLINE_OFFSET]);
+    let deadline =
+        U64::from_big_endian(&calldata[SYSCALL_DEADLINE_OFFSET..SYSCALL_PAYLOAD_LEN_OFFSET]);
+    let payload = &calldata[SYSCALL_PAYLOAD_OFFSET..payload_end];
 
-    let sigs_len = calldata.len() - (79 + payload_len);
-    if sigs_len % 65 != 0 {
-        return Err(Error::MalformedCalldata)
+    let packed_sigs = &calldata[payload_end..];
+    if !packed_sigs.len().is_multiple_of(SYSCALL_SIGNATURE_LEN) {
+        return Err(Error::MalformedCalldata);
     }
 
-    let mut sigs = Vec::with_capacity(sigs_len / 65);
-
-    for i in 0..sigs_len {
-        let sig_start = 79 + payload_len + (i * 65);
-        let sig_stop = 79 + payload_len + ((i + 1) * 65);
-        // we unwrap here, as the only fail case is `bytes.len() != 65`, which we have already
-        // validated.
-        let sig = Signature::try_from(&calldata[sig_start..sig_stop]).unwrap();
-        sigs.push(sig);
-    }
+    let sigs = packed_sigs
+        .chunks_exact(SYSCALL_SIGNATURE_LEN)
+        .map(DeckSignature::from_bytes)
+        .collect::<Result<Vec<DeckSignature>, Error>>()?;
 
     Ok(Syscall::new(&id, &target, &value, &deadline, payload, &sigs))
 }
 
 #[cfg(test)]
 mod tests {
-    // TODO: test these lol
+    use super::*;
+    use crate::{
+        coder::encoder::{
+            encode, encode_set_auth, encode_set_shard, encode_set_threshold, encode_syscall,
+        },
+        deploy::ETHER_DECK_BYTECODE,
+    };
+
+    fn signature(seed: u8) -> DeckSignature {
+        DeckSignature::new(27, &U256::from(seed), &(U256::from(seed) + 1))
+    }
+
+    /// Returns true if the deck bytecode contains `PUSH1 offset; CALLDATALOAD`.
+    fn bytecode_loads(offset: usize) -> bool {
+        ETHER_DECK_BYTECODE
+            .windows(3)
+            .any(|ops| ops == [0x60, offset as u8, 0x35])
+    }
+
+    #[test]
+    fn test_decode_set_auth() {
+        let account = Address::repeat_byte(0xf3);
+        let encoded = encode_set_auth(&account, true);
+
+        assert_eq!(
+            decode(&encoded).unwrap(),
+            Encoding::SetAuth(SetAuth::new(&account, true))
+        );
+    }
+
+    #[test]
+    fn test_decode_set_threshold() {
+        let encoded = encode_set_threshold(3);
+
+        assert_eq!(
+            decode(&encoded).unwrap(),
+            Encoding::SetThreshold(SetThreshold::new(3))
+        );
+    }
+
+    #[test]
+    fn test_decode_set_shard() {
+        let shard = Address::repeat_byte(0x5d);
+        let encoded = encode_set_shard(&[1, 2, 3, 4], &shard);
+
+        assert_eq!(
+            decode(&encoded).unwrap(),
+            Encoding::SetShard(SetShard::new(&[1, 2, 3, 4], &shard))
+        );
+    }
+
+    #[test]
+    fn test_decode_dispatch_syscall() {
+        let encoded = encode_syscall(
+            &U256::one(),
+            &Address::repeat_byte(0x01),
+            &U256::zero(),
+            &U64::one(),
+            &[0xab],
+            &[signature(1)],
+        )
+        .unwrap();
+
+        assert_eq!(
+            decode(&encoded).unwrap(),
+            Encoding::Syscall(decode_syscall(&encoded).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_decode_shard_call() {
+        let calldata = [0xa9, 0x05, 0x9c, 0xbb, 0x01, 0x02];
+        let encoding = decode(&calldata).unwrap();
+
+        assert_eq!(
+            encoding,
+            Encoding::ShardCall {
+                selector: [0xa9, 0x05, 0x9c, 0xbb],
+                data: vec![0x01, 0x02],
+            }
+        );
+        assert_eq!(encode(&encoding).unwrap(), calldata);
+    }
+
+    #[test]
+    fn test_decode_shard_call_reserved_zero() {
+        assert_eq!(
+            decode(&[0, 0, 0, 0]).unwrap(),
+            Encoding::ShardCall {
+                selector: [0, 0, 0, 0],
+                data: vec![],
+            }
+        );
+    }
+
+    #[test]
+    fn test_decode_short_calldata() {
+        assert_eq!(decode(&[0, 0, 0]), Err(Error::CalldataLength));
+    }
+
+    #[test]
+    fn test_decode_reserved_length_mismatch() {
+        assert_eq!(decode(&[0, 0, 0, 1, 0]), Err(Error::CalldataLength));
+    }
+
+    #[test]
+    fn test_syscall_offsets_match_bytecode() {
+        assert!(bytecode_loads(SYSCALL_TARGET_OFFSET));
+        assert!(bytecode_loads(SYSCALL_VALUE_OFFSET));
+        assert!(bytecode_loads(SYSCALL_DEADLINE_OFFSET));
+        assert!(bytecode_loads(SYSCALL_PAYLOAD_LEN_OFFSET));
+        // payload copy: `PUSH1 0x4f; PUSH1 0x00; CALLDATACOPY`
+        assert!(ETHER_DECK_BYTECODE
+            .windows(5)
+            .any(|ops| ops == [0x60, SYSCALL_PAYLOAD_OFFSET as u8, 0x60, 0x00, 0x37]));
+    }
+
+    #[test]
+    fn test_decode_syscall_golden() {
+        let mut calldata = vec![0, 0, 0, 4];
+        calldata.extend_from_slice(&[0u8; 31]);
+        calldata.push(7);
+        calldata.extend_from_slice(&[0xaa; 20]);
+        calldata.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 0, 0, 0x01, 0x00]);
+        calldata.extend_from_slice(&[0, 0, 0, 0, 0x64, 0x00, 0x00, 0x00]);
+        calldata.extend_from_slice(&[0, 0, 0, 3]);
+        calldata.extend_from_slice(&[0xde, 0xad, 0xbe]);
+        calldata.push(27);
+        calldata.extend_from_slice(&[0u8; 31]);
+        calldata.push(1);
+        calldata.extend_from_slice(&[0u8; 31]);
+        calldata.push(2);
+        assert_eq!(calldata.len(), SYSCALL_PAYLOAD_OFFSET + 3 + SYSCALL_SIGNATURE_LEN);
+
+        let syscall = decode_syscall(&calldata).unwrap();
+
+        assert_eq!(syscall.id(), &U256::from(7));
+        assert_eq!(syscall.target(), &Address::repeat_byte(0xaa));
+        assert_eq!(syscall.value(), &U256::from(256));
+        assert_eq!(syscall.deadline(), &U64::from(0x6400_0000u64));
+        assert_eq!(syscall.payload(), &[0xde, 0xad, 0xbe]);
+        assert_eq!(syscall.signatures(), &[signature(1)]);
+    }
+
+    #[test]
+    fn test_decode_syscall_round_trip() {
+        let syscall = Syscall::new(
+            &U256::from(42),
+            &Address::repeat_byte(0x11),
+            &((U256::one() << 88) - 1),
+            &U64::MAX,
+            &[1, 2, 3, 4, 5],
+            &[signature(1), signature(2), signature(3)],
+        );
+        let encoded = encode_syscall(
+            syscall.id(),
+            syscall.target(),
+            syscall.value(),
+            syscall.deadline(),
+            syscall.payload(),
+            syscall.signatures(),
+        )
+        .unwrap();
+
+        assert_eq!(decode_syscall(&encoded).unwrap(), syscall);
+    }
+
+    #[test]
+    fn test_decode_syscall_round_trip_empty() {
+        let syscall = Syscall::new(
+            &U256::zero(),
+            &Address::zero(),
+            &U256::zero(),
+            &U64::zero(),
+            &[],
+            &[],
+        );
+        let encoded = encode_syscall(
+            syscall.id(),
+            syscall.target(),
+            syscall.value(),
+            syscall.deadline(),
+            syscall.payload(),
+            syscall.signatures(),
+        )
+        .unwrap();
+
+        assert_eq!(encoded.len(), SYSCALL_PAYLOAD_OFFSET);
+        assert_eq!(decode_syscall(&encoded).unwrap(), syscall);
+    }
+
+    #[test]
+    fn test_decode_syscall_short() {
+        assert_eq!(
+            decode_syscall(&[0u8; SYSCALL_PAYLOAD_OFFSET - 1]),
+            Err(Error::CalldataLength)
+        );
+    }
+
+    #[test]
+    fn test_decode_syscall_payload_overrun() {
+        let mut calldata = [0u8; SYSCALL_PAYLOAD_OFFSET + 2];
+        calldata[SYSCALL_PAYLOAD_OFFSET - 1] = 3;
+        assert_eq!(decode_syscall(&calldata), Err(Error::CalldataLength));
+    }
+
+    #[test]
+    fn test_decode_syscall_partial_signature() {
+        let calldata = [0u8; SYSCALL_PAYLOAD_OFFSET + SYSCALL_SIGNATURE_LEN - 1];
+        assert_eq!(decode_syscall(&calldata), Err(Error::MalformedCalldata));
+    }
 }
@@ -0,0 +1,198 @@
+//! # Differential Tests
+//!
+//! Encodes random calls with the [encoder](crate::coder::encoder), executes them against the
+//! deck's bytecode in the [Simulator](crate::simulate::Simulator), and checks the storage and
+//! logs the bytecode writes against the fields the [decoder](crate::coder::decoder) reads back.
+//!
+//! Governance calls are gated to the deck, so each is executed as the payload of a syscall to the
+//! deck itself. Cases are generated from fixed seeds so failures are reproducible.
+use crate::{
+    coder::{
+        decoder::decode,
+        encoder::encode,
+        structures::{Encoding, SetAuth, SetShard, SetThreshold, Syscall},
+    },
+    events::{
+        decode_log, AuthSetEvent, DeckEvent, ShardSetEvent, SyscallEvent, ThresholdSetEvent,
+    },
+    simulate::{
+        fixture::{deck_with_owners, execute_syscall, RECORDER},
+        Outcome, Simulator,
+    },
+    storage::slot::{auth_slot, id_slot, shard_slot, threshold_slot},
+    util::u256_to_be,
+};
+use ethers::{
+    prelude::{Address, H256, U256, U64},
+    signers::{LocalWallet, Signer},
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+const CASES: u64 = 32;
+const CHAIN_ID: u64 = 31337;
+
+/// A deck deployed by random owners.
+struct Fixture {
+    sim: Simulator,
+    deck: Address,
+    owners: Vec<LocalWallet>,
+}
+
+impl Fixture {
+    /// Deploys a deck and authorizes between one and four owners at a random id.
+    fn new(rng: &mut StdRng) -> Self {
+        let mut sim = Simulator::new(CHAIN_ID);
+        let deployer = Address::random_using(rng);
+        let owners = (0..rng.gen_range(1..=4))
+            .map(|_| LocalWallet::new(rng))
+            .collect::<Vec<LocalWallet>>();
+        let addresses = owners.iter().map(Signer::address).collect::<Vec<Address>>();
+        let id = U256::from_big_endian(&rng.gen::<[u8; 32]>());
+        let deck = deck_with_owners(&mut sim, &deployer, &addresses, owners.len() as u8, &id);
+
+        Self { sim, deck, owners }
+    }
+
+    /// Returns the deck's id.
+    fn id(&self) -> U256 {
+        U256::from_big_endian(self.sim.storage(&self.deck, &u256_to_be(&id_slot())).as_bytes())
+    }
+
+    /// Signs, encodes, and executes a syscall, returning the decoded syscall and the outcome.
+    async fn execute(
+        &mut self,
+        target: &Address,
+        value: &U256,
+        deadline: &U64,
+        payload: &[u8],
+    ) -> (Syscall, Outcome) {
+        let unsigned = Syscall::new(&self.id(), target, value, deadline, payload, &[]);
+        let (signed, outcome) =
+            execute_syscall(&mut self.sim, &self.deck, &unsigned, &self.owners).await;
+
+        let calldata = encode(&Encoding::Syscall(signed.clone())).unwrap();
+        let decoded = match decode(&calldata).unwrap() {
+            Encoding::Syscall(decoded) => decoded,
+            _ => panic!("expected syscall"),
+        };
+        assert_eq!(decoded, signed);
+        assert!(outcome.is_success(), "{:?}", outcome);
+        (decoded, outcome)
+    }
+
+    /// Executes a governance call and returns its decoded encoding and events.
+    async fn govern(&mut self, rng: &mut StdRng, call: &Encoding) -> (Encoding, Vec<DeckEvent>) {
+        let payload = encode(call).unwrap();
+        let decoded = decode(&payload).unwrap();
+        assert_eq!(&decoded, call);
+
+        let (deck, deadline) = (self.deck, deadline(rng));
+        let (_, outcome) = self.execute(&deck, &U256::zero(), &deadline, &payload).await;
+        let events = outcome
+            .logs()
+            .iter()
+            .map(|log| decode_log(log).unwrap())
+            .collect::<Vec<DeckEvent>>();
+        (decoded, events)
+    }
+}
+
+/// Returns a deadline after the simulator's timestamp.
+fn deadline(rng: &mut StdRng) -> U64 {
+    U64::from(rng.gen_range(2..=u64::MAX))
+}
+
+#[tokio::test]
+async fn test_set_auth() {
+    let mut rng = StdRng::seed_from_u64(1);
+
+    for _ in 0..CASES {
+        let mut fixture = Fixture::new(&mut rng);
+        let call = Encoding::SetAuth(SetAuth::new(&Address::random_using(&mut rng), rng.gen()));
+
+        let (decoded, events) = fixture.govern(&mut rng, &call).await;
+        let Encoding::SetAuth(decoded) = decoded else { panic!("expected setAuth") };
+
+        let stored = fixture.sim.storage(&fixture.deck, &auth_slot(decoded.account()));
+        assert_eq!(stored, H256::from_low_u64_be(decoded.authorized() as u64));
+        assert_eq!(
+            events[1],
+            DeckEvent::AuthSet(AuthSetEvent::new(decoded.account(), decoded.authorized()))
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_set_threshold() {
+    let mut rng = StdRng::seed_from_u64(2);
+
+    for _ in 0..CASES {
+        let mut fixture = Fixture::new(&mut rng);
+        let call = Encoding::SetThreshold(SetThreshold::new(rng.gen()));
+
+        let (decoded, events) = fixture.govern(&mut rng, &call).await;
+        let Encoding::SetThreshold(decoded) = decoded else { panic!("expected setThreshold") };
+
+        let stored = fixture.sim.storage(&fixture.deck, &u256_to_be(&threshold_slot()));
+        assert_eq!(stored, H256::from_low_u64_be(decoded.threshold() as u64));
+        assert_eq!(
+            events[1],
+            DeckEvent::ThresholdSet(ThresholdSetEvent::new(decoded.threshold()))
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_set_shard() {
+    let mut rng = StdRng::seed_from_u64(3);
+
+    for _ in 0..CASES {
+        let mut fixture = Fixture::new(&mut rng);
+        let selector = rng.gen::<[u8; 4]>();
+        let call = Encoding::SetShard(SetShard::new(&selector, &Address::random_using(&mut rng)));
+
+        let (decoded, events) = fixture.govern(&mut rng, &call).await;
+        let Encoding::SetShard(decoded) = decoded else { panic!("expected setShard") };
+
+        let stored = fixture.sim.storage(&fixture.deck, &shard_slot(decoded.selector()));
+        assert_eq!(stored, H256::from(*decoded.shard()));
+        assert_eq!(
+            events[1],
+            DeckEvent::ShardSet(ShardSetEvent::new(decoded.selector(), decoded.shard()))
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_syscall() {
+    let mut rng = StdRng::seed_from_u64(4);
+
+    for _ in 0..CASES {
+        let mut fixture = Fixture::new(&mut rng);
+        let target = Address::random_using(&mut rng);
+        let value = U256::from(rng.gen::<u128>() >> 40);
+        let payload = (0..rng.gen_range(0..=256))
+            .map(|_| rng.gen())
+            .collect::<Vec<u8>>();
+        let id = fixture.id();
+
+        fixture.sim.set_code(&target, &RECORDER);
+        fixture.sim.set_balance(&fixture.deck, &(U256::one() << 88));
+
+        let (decoded, outcome) = fixture
+            .execute(&target, &value, &deadline(&mut rng), &payload)
+            .await;
+
+        assert_eq!(decoded.id(), &id);
+        assert_eq!(fixture.id(), decoded.id().overflowing_add(U256::one()).0);
+        assert_eq!(
+            decode_log(&outcome.logs()[0]).unwrap(),
+            DeckEvent::Syscall(SyscallEvent::new(decoded.id()))
+        );
+
+        let inner = &outcome.logs()[1];
+        assert_eq!(&inner.address, decoded.target());
+        assert_eq!(inner.data.as_ref(), decoded.payload());
+        assert_eq!(fixture.sim.balance(decoded.target()), *decoded.value());
+    }
+}
@@ -2,13 +2,25 @@
 //! 
 //! Contains functions for encoding each of the Ether Deck contract's methods
 use crate::{
-    coder::structures::Encoding,
-    constants::{SELECTOR_SET_AUTH, SELECTOR_SET_SHARD, SELECTOR_SET_THRESHOLD, SELECTOR_SYSCALL},
+    coder::structures::{DeckSignature, Encoding},
+    constants::{
+        SELECTOR_SET_AUTH, SELECTOR_SET_SHARD, SELECTOR_SET_THRESHOLD, SELECTOR_SYSCALL,
+        SYSCALL_PAYLOAD_OFFSET, SYSCALL_SIGNATURE_LEN,
+    },
     error::Error,
     util::{u256_to_be, u64_to_be},
 };
-use ethers::prelude::{U256, U64, Address, Signature};
+use ethers::prelude::{U256, U64, Address};
 
+/// ## Encode Calldata
+/// 
+/// ### Parameters
+/// 
+/// - `encoding`: The encoding to encode.
+/// 
+/// ### Returns
+/// 
+/// Either the encoded calldata or an [Error](crate::error::Error).
 pub fn encode(encoding: &Encoding) -> Result<Vec<u8>, Error> {
     let encoded = match encoding {
         Encoding::SetAuth(data) => encode_set_auth(data.account(), data.authorized()),
@@ -22,6 +34,7 @@ pub fn encode(encoding: &Encoding) -> Result<Vec<u8>, Error> {
             data.payload(),
             data.signatures(),
         )?,
+        Encoding::ShardCall { selector, data } => encode_shard_call(selector, data)?,
     };
 
     Ok(encoded)
@@ -88,7 +101,7 @@ pub fn encode_set_shard(selector: &[u8; 4], shard: &Address) -> Vec<u8> {
 /// - `value`: The value to send.
 /// - `deadline`: The deadline for the call.
 /// - `payload`: The payload to send.
-/// - `signatures`: The signatures to include.
+/// - `signatures`: The signatures to include, packed as `v || r || s`.
 /// 
 /// ### Returns
 /// 
@@ -99,9 +112,9 @@ pub fn encode_syscall(
     value: &U256,
     deadline: &U64,
     payload: &[u8],
-    signatures: &[Signature],
+    signatures: &[DeckSignature],
 ) -> Result<Vec<u8>, Error> {
-    if value > &U256::from("309485009821345068724781055") {
+    if *value > (U256::one() << 88) - 1 {
         return Err(Error::CallValueOverflow);
     }
 
@@ -113,15 +126,16 @@ pub fn encode_syscall(
 
     let packed_sigs: Vec<u8> = signatures
         .iter()
-        .map(|sig| <[u8; 65]>::from(sig))
-        .flatten()
+        .flat_map(DeckSignature::to_bytes)
         .collect();
 
-    let mut encoded = Vec::with_capacity(100 + payload.len() + 65 * signatures.len());
+    let mut encoded = Vec::with_capacity(
+        SYSCALL_PAYLOAD_OFFSET + payload.len() + SYSCALL_SIGNATURE_LEN * signatures.len(),
+    );
     encoded.extend_from_slice(&SELECTOR_SYSCALL);
     encoded.extend_from_slice(&u256_to_be(id));
     encoded.extend_from_slice(&target.to_fixed_bytes());
-    encoded.extend_from_slice(&value_u88);
+    encoded.extend_from_slice(value_u88);
     encoded.extend_from_slice(&u64_to_be(deadline));
     encoded.extend_from_slice((payload.len() as u32).to_be_bytes().as_ref());
     encoded.extend_from_slice(payload);
@@ -130,6 +144,33 @@ pub fn encode_syscall(
     Ok(encoded)
 }
 
+/// ## Encode Shard Call
+/// 
+/// The deck dispatches its own selectors before the fallback, so a shard call with a reserved
+/// selector would never reach a shard.
+/// 
+/// ### Parameters
+/// 
+/// - `selector`: The shard selector.
+/// - `data`: The calldata following the selector.
+/// 
+/// ### Returns
+/// 
+/// Either the encoded calldata or an [Error](crate::error::Error) if the selector is reserved.
+pub fn encode_shard_call(selector: &[u8; 4], data: &[u8]) -> Result<Vec<u8>, Error> {
+    let reserved = [
+        SELECTOR_SET_AUTH,
+        SELECTOR_SET_THRESHOLD,
+        SELECTOR_SET_SHARD,
+        SELECTOR_SYSCALL,
+    ];
+    if reserved.contains(selector) {
+        return Err(Error::ReservedSelector(*selector));
+    }
+
+    Ok([selector.as_slice(), data].concat())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -171,5 +212,32 @@ mod tests {
         assert_eq!(encoded[8..28], shard.to_fixed_bytes());
     }
 
-    // TODO: test encode_syscall
+    #[test]
+    fn test_encode_syscall_value_overflow() {
+        let max = (U256::one() << 88) - 1;
+        let encode = |value: &U256| {
+            encode_syscall(&U256::zero(), &Address::zero(), value, &U64::zero(), &[], &[])
+        };
+
+        assert_eq!(encode(&max).unwrap()[56..67], [0xff; 11]);
+        assert_eq!(encode(&(max + 1)), Err(Error::CallValueOverflow));
+    }
+
+    #[test]
+    fn test_encode_shard_call() {
+        let encoding = |selector| Encoding::ShardCall {
+            selector,
+            data: vec![0xaa],
+        };
+
+        assert_eq!(encode(&encoding([1, 2, 3, 5])).unwrap(), vec![1, 2, 3, 5, 0xaa]);
+        for selector in [
+            SELECTOR_SET_AUTH,
+            SELECTOR_SET_THRESHOLD,
+            SELECTOR_SET_SHARD,
+            SELECTOR_SYSCALL,
+        ] {
+            assert_eq!(encode(&encoding(selector)), Err(Error::ReservedSelector(selector)));
+        }
+    }
 }
@@ -1,3 +1,6 @@
 pub mod encoder;
 pub mod decoder;
 pub mod structures;
+
+#[cfg(test)]
+mod differential;
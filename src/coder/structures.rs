@@ -1,9 +1,11 @@
 //! # Ether Deck Coder Structures
 //! 
 //! Contains data structures for encoding and decoding each of the Ether Deck contract's methods.
+use crate::{constants::SYSCALL_SIGNATURE_LEN, error::Error, util::u256_to_be};
 use ethers::prelude::{U256, U64, Address, Signature};
 
 /// ## Set Auth Encoding
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct SetAuth {
     account: Address,
     authorized: bool,
@@ -32,6 +34,7 @@ impl SetAuth {
 }
 
 /// ## Set Threshold Encoding
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct SetThreshold {
     pub threshold: u8,
 }
@@ -53,6 +56,7 @@ impl SetThreshold {
 }
 
 /// ## Set Shard Encoding
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct SetShard {
     selector: [u8; 4],
     shard: Address,
@@ -83,14 +87,120 @@ impl SetShard {
     }
 }
 
+/// ## Deck Signature
+///
+/// An ECDSA signature in the deck's packed `v || r || s` layout. The deck reads the recovery byte
+/// from the first byte of each signature, unlike the `r || s || v` layout used by ethers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DeckSignature {
+    v: u8,
+    r: U256,
+    s: U256,
+}
+
+impl DeckSignature {
+    /// ### Create New Deck Signature
+    /// 
+    /// #### Parameters
+    /// 
+    /// - `v`: The recovery byte (27 or 28).
+    /// - `r`: The `r` value.
+    /// - `s`: The `s` value.
+    pub fn new(v: u8, r: &U256, s: &U256) -> Self {
+        Self { v, r: *r, s: *s }
+    }
+
+    /// ### Decode Packed Signature
+    /// 
+    /// #### Parameters
+    /// 
+    /// - `bytes`: The packed `v || r || s` signature.
+    /// 
+    /// #### Returns
+    /// 
+    /// Either a [DeckSignature] or an [Error](crate::error::Error).
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        if bytes.len() != SYSCALL_SIGNATURE_LEN {
+            return Err(Error::MalformedCalldata);
+        }
+
+        Ok(Self {
+            v: bytes[0],
+            r: U256::from_big_endian(&bytes[1..33]),
+            s: U256::from_big_endian(&bytes[33..65]),
+        })
+    }
+
+    /// ### Encode Packed Signature
+    /// 
+    /// #### Returns
+    /// 
+    /// The packed `v || r || s` signature.
+    pub fn to_bytes(&self) -> [u8; 65] {
+        let mut bytes = [0u8; 65];
+        bytes[0] = self.v;
+        bytes[1..33].copy_from_slice(&u256_to_be(&self.r));
+        bytes[33..65].copy_from_slice(&u256_to_be(&self.s));
+        bytes
+    }
+
+    /// ### Get the recovery byte
+    pub fn v(&self) -> u8 {
+        self.v
+    }
+
+    /// ### Get the `r` value
+    pub fn r(&self) -> &U256 {
+        &self.r
+    }
+
+    /// ### Get the `s` value
+    pub fn s(&self) -> &U256 {
+        &self.s
+    }
+}
+
+impl TryFrom<Signature> for DeckSignature {
+    type Error = Error;
+
+    /// Normalizes the recovery id to the 27 or 28 expected by the `ecrecover` precompile, failing
+    /// for any `v` that is not a recovery id.
+    fn try_from(sig: Signature) -> Result<Self, Error> {
+        let v = match sig.v {
+            0 | 1 => sig.v + 27,
+            27 | 28 => sig.v,
+            // EIP-155: `v = chain_id * 2 + 35 + recovery_id`
+            v if v >= 35 => (v - 35) % 2 + 27,
+            v => return Err(Error::InvalidRecoveryId(v)),
+        };
+
+        Ok(Self {
+            v: v as u8,
+            r: sig.r,
+            s: sig.s,
+        })
+    }
+}
+
+impl From<DeckSignature> for Signature {
+    fn from(sig: DeckSignature) -> Self {
+        Self {
+            r: sig.r,
+            s: sig.s,
+            v: sig.v as u64,
+        }
+    }
+}
+
 /// ## Syscall Encoding
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Syscall {
     id: U256,
     target: Address,
     value: U256,
     deadline: U64,
     payload: Vec<u8>,
-    signatures: Vec<Signature>,
+    signatures: Vec<DeckSignature>,
 }
 
 impl Syscall {
@@ -110,7 +220,7 @@ impl Syscall {
         value: &U256,
         deadline: &U64,
         payload: &[u8],
-        signatures: &[Signature],
+        signatures: &[DeckSignature],
     ) -> Self {
         Self {
             id: *id,
@@ -148,12 +258,13 @@ impl Syscall {
     }
 
     /// ### Get the signature list
-    pub fn signatures(&self) -> &[Signature] {
+    pub fn signatures(&self) -> &[DeckSignature] {
         &self.signatures
     }
 }
 
 /// ## Encoding Enum
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Encoding {
     /// Set Auth Encoding
     SetAuth(SetAuth),
@@ -162,5 +273,87 @@ pub enum Encoding {
     /// Set Shard Encoding
     SetShard(SetShard),
     /// Syscall Encoding
-    Syscall(Syscall)
-}
\ No newline at end of file
+    Syscall(Syscall),
+    /// Shard Call Encoding
+    ///
+    /// Any non-reserved selector, routed by the deck's fallback to the selector's shard.
+    ShardCall {
+        /// The shard selector.
+        selector: [u8; 4],
+        /// The calldata following the selector.
+        data: Vec<u8>,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deck_signature_layout() {
+        let sig = DeckSignature::new(28, &U256::from(1), &U256::from(2));
+        let bytes = sig.to_bytes();
+
+        assert_eq!(bytes[0], 28);
+        assert_eq!(U256::from_big_endian(&bytes[1..33]), U256::from(1));
+        assert_eq!(U256::from_big_endian(&bytes[33..65]), U256::from(2));
+        assert_eq!(DeckSignature::from_bytes(&bytes).unwrap(), sig);
+    }
+
+    #[test]
+    fn test_deck_signature_v_first_in_bytecode() {
+        // signature loop: `DUP1; CALLDATALOAD; PUSH1 0xf8; SHR` reads `v` from the first byte.
+        assert!(crate::deploy::ETHER_DECK_BYTECODE
+            .windows(5)
+            .any(|ops| ops == [0x80, 0x35, 0x60, 0xf8, 0x1c]));
+    }
+
+    #[test]
+    fn test_deck_signature_from_bytes_length() {
+        assert_eq!(DeckSignature::from_bytes(&[0u8; 64]), Err(Error::MalformedCalldata));
+    }
+
+    #[test]
+    fn test_deck_signature_from_ethers() {
+        let sig = Signature {
+            r: U256::from(3),
+            s: U256::from(4),
+            v: 27,
+        };
+        let deck_sig = DeckSignature::try_from(sig).unwrap();
+
+        assert_eq!(deck_sig.to_bytes()[0], 27);
+        assert_eq!(deck_sig.to_bytes()[1..], <[u8; 65]>::from(&sig)[..64]);
+        assert_eq!(Signature::from(deck_sig), sig);
+    }
+
+    #[test]
+    fn test_deck_signature_normalizes_v() {
+        let sig = |v| Signature {
+            r: U256::one(),
+            s: U256::one(),
+            v,
+        };
+
+        assert_eq!(DeckSignature::try_from(sig(0)).unwrap().v(), 27);
+        assert_eq!(DeckSignature::try_from(sig(1)).unwrap().v(), 28);
+        assert_eq!(DeckSignature::try_from(sig(28)).unwrap().v(), 28);
+        // chain id 1, recovery id 1
+        assert_eq!(DeckSignature::try_from(sig(38)).unwrap().v(), 28);
+        // EIP-155 with the largest chain id
+        assert_eq!(DeckSignature::try_from(sig(u64::MAX)).unwrap().v(), 27);
+    }
+
+    #[test]
+    fn test_deck_signature_rejects_invalid_v() {
+        let sig = |v| Signature {
+            r: U256::one(),
+            s: U256::one(),
+            v,
+        };
+
+        for v in [2, 26, 29, 34] {
+            assert_eq!(DeckSignature::try_from(sig(v)), Err(Error::InvalidRecoveryId(v)));
+        }
+    }
+}
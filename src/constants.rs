@@ -1,6 +1,7 @@
 //! Ether Deck Constants
 //! 
-//! Contains reserved function selectors and storage indices.
+//! Contains reserved function selectors, error selectors, event topics, storage indices, and
+//! syscall calldata offsets.
 //! 
 //! > Note: Storage indices are not necessarily the final storage slot. Storage mappings require
 //! > additional processing. See the [storage module](crate::storage) for storage slot computation.
@@ -17,6 +18,55 @@ pub const SELECTOR_SET_SHARD: [u8; 4] = [0, 0, 0, 3];
 /// ## Syscall Selector
 pub const SELECTOR_SYSCALL: [u8; 4] = [0, 0, 0, 4];
 
+/// ## Shard Not Found Error Selector
+pub const ERROR_SHARD_NOT_FOUND: [u8; 4] = [0xef, 0x16, 0x0e, 0x8e];
+
+/// ## Deadline Expired Error Selector
+pub const ERROR_DEADLINE_EXPIRED: [u8; 4] = [0xc6, 0xf2, 0x21, 0xaa];
+
+/// ## Unauthorized Error Selector
+pub const ERROR_UNAUTHORIZED: [u8; 4] = [0xab, 0x8a, 0x03, 0x60];
+
+/// ## Syscall Event Topic
+///
+/// `Syscall(uint256)`
+pub const TOPIC_SYSCALL: [u8; 32] = [
+    0xd5, 0x65, 0x24, 0x1e, 0x29, 0x36, 0x34, 0xc5,
+    0x09, 0xaf, 0x5a, 0x0f, 0x5f, 0x7b, 0x4c, 0x3f,
+    0xea, 0x30, 0xfb, 0x14, 0x14, 0x78, 0xd0, 0xc9,
+    0xf9, 0xa2, 0xe2, 0xd2, 0xe6, 0xa3, 0xcf, 0x7c,
+];
+
+/// ## AuthSet Event Topic
+///
+/// `AuthSet(address,bool)`
+pub const TOPIC_AUTH_SET: [u8; 32] = [
+    0x5b, 0x11, 0x78, 0x62, 0x68, 0x98, 0x64, 0xea,
+    0xda, 0x25, 0x61, 0x17, 0x27, 0x62, 0xc0, 0xae,
+    0x6d, 0x3e, 0x90, 0x16, 0xf3, 0xe6, 0x3d, 0x39,
+    0x28, 0xc0, 0x20, 0xee, 0xdc, 0x26, 0xe7, 0xc1,
+];
+
+/// ## ThresholdSet Event Topic
+///
+/// `ThresholdSet(uint8)`
+pub const TOPIC_THRESHOLD_SET: [u8; 32] = [
+    0x19, 0xbd, 0xbd, 0x56, 0xc4, 0xc0, 0x49, 0xb2,
+    0xd3, 0xa1, 0x18, 0x69, 0x73, 0xe7, 0x69, 0x05,
+    0xfb, 0x68, 0x43, 0xc0, 0xed, 0xe2, 0x9b, 0x88,
+    0x1e, 0x1e, 0x2a, 0x34, 0xe3, 0xbd, 0xbd, 0x9a,
+];
+
+/// ## ShardSet Event Topic
+///
+/// `ShardSet(bytes4,address)`
+pub const TOPIC_SHARD_SET: [u8; 32] = [
+    0x92, 0x65, 0x51, 0x64, 0x11, 0x85, 0x43, 0x1a,
+    0x7a, 0xd6, 0x58, 0x51, 0x2f, 0xae, 0x1a, 0x08,
+    0x8f, 0x1c, 0xef, 0xda, 0x6f, 0xf1, 0xb7, 0xe4,
+    0x80, 0xeb, 0x55, 0x9e, 0x9c, 0x47, 0x00, 0xb8,
+];
+
 /// ## ID Storage Index
 pub const ID_INDEX: usize = 0;
 
@@ -28,3 +78,24 @@ pub const AUTH_INDEX: usize = 2;
 
 /// ## Shard Storage Index
 pub const SHARD_INDEX: usize = 3;
+
+/// ## Syscall ID Calldata Offset
+pub const SYSCALL_ID_OFFSET: usize = 0x04;
+
+/// ## Syscall Target Calldata Offset
+pub const SYSCALL_TARGET_OFFSET: usize = 0x24;
+
+/// ## Syscall Value Calldata Offset
+pub const SYSCALL_VALUE_OFFSET: usize = 0x38;
+
+/// ## Syscall Deadline Calldata Offset
+pub const SYSCALL_DEADLINE_OFFSET: usize = 0x43;
+
+/// ## Syscall Payload Length Calldata Offset
+pub const SYSCALL_PAYLOAD_LEN_OFFSET: usize = 0x4b;
+
+/// ## Syscall Payload Calldata Offset
+pub const SYSCALL_PAYLOAD_OFFSET: usize = 0x4f;
+
+/// ## Syscall Signature Length
+pub const SYSCALL_SIGNATURE_LEN: usize = 65;
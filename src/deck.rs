@@ -0,0 +1,423 @@
+//! # Ether Deck Client Module
+//!
+//! Contains a handle tying a deployed deck's address to a middleware.
+use crate::{
+    bytecode::{verify_code, Verdict},
+    coder::{
+        encoder::{encode, encode_shard_call},
+        structures::{Encoding, Syscall},
+    },
+    error::Error,
+    governance,
+    preflight::{preflight_syscall, Preflight},
+    revert::{decode_middleware_error, decode_receipt_revert},
+    sign,
+    storage::{
+        reader::{
+            read_auth_at, read_id_at, read_shard_at, read_shard_status_at, read_threshold_at,
+            ShardStatus,
+        },
+        state::{read_state, DeckState},
+    },
+    verify::{self, SyscallReport},
+};
+use ethers::{prelude::*, types::transaction::eip2718::TypedTransaction};
+
+/// ## Deck
+///
+/// Reads are made at the default block, `latest` if unset. Transactions are sent from the
+/// middleware's default sender and awaited for the confirmation count. Syscalls are only signed
+/// for the deck's exact runtime code or one of the configured known versions.
+#[derive(Debug, Clone)]
+pub struct Deck<M> {
+    client: M,
+    address: Address,
+    block: Option<BlockId>,
+    confirmations: usize,
+    known_versions: Vec<H256>,
+}
+
+impl<M> Deck<M>
+where
+    M: Middleware + Clone + 'static,
+{
+    /// ### Create New Deck
+    ///
+    /// Reads at `latest`, awaits one confirmation and knows no other deck versions.
+    ///
+    /// #### Parameters
+    ///
+    /// - `client`: The middleware to read and send with.
+    /// - `address`: The deck's address.
+    pub fn new(client: M, address: &Address) -> Self {
+        Self {
+            client,
+            address: *address,
+            block: None,
+            confirmations: 1,
+            known_versions: Vec::new(),
+        }
+    }
+
+    /// ### Set the default block
+    pub fn with_block(mut self, block: Option<BlockId>) -> Self {
+        self.block = block;
+        self
+    }
+
+    /// ### Set the confirmation count
+    pub fn with_confirmations(mut self, confirmations: usize) -> Self {
+        self.confirmations = confirmations;
+        self
+    }
+
+    /// ### Set the runtime code hashes of other deck versions to sign for
+    pub fn with_known_versions(mut self, known_versions: &[H256]) -> Self {
+        self.known_versions = known_versions.to_vec();
+        self
+    }
+
+    /// ### Get the middleware
+    pub fn client(&self) -> &M {
+        &self.client
+    }
+
+    /// ### Get the deck's address
+    pub fn address(&self) -> &Address {
+        &self.address
+    }
+
+    /// ### Get the default block
+    pub fn block(&self) -> Option<BlockId> {
+        self.block
+    }
+
+    /// ### Get the confirmation count
+    pub fn confirmations(&self) -> usize {
+        self.confirmations
+    }
+
+    /// ### Get the runtime code hashes of other deck versions
+    pub fn known_versions(&self) -> &[H256] {
+        &self.known_versions
+    }
+
+    /// ### Verify the code at the deck's address
+    pub async fn verify_code(&self) -> Result<Verdict, Error> {
+        verify_code(self.client.clone(), &self.address, &self.known_versions, self.block).await
+    }
+
+    /// ### Read the syscall id
+    pub async fn id(&self) -> Result<U256, Error> {
+        read_id_at(self.client.clone(), &self.address, self.block).await
+    }
+
+    /// ### Read the threshold
+    pub async fn threshold(&self) -> Result<u8, Error> {
+        read_threshold_at(self.client.clone(), &self.address, self.block).await
+    }
+
+    /// ### Read whether an account is authorized
+    pub async fn auth(&self, account: &Address) -> Result<bool, Error> {
+        read_auth_at(self.client.clone(), &self.address, account, self.block).await
+    }
+
+    /// ### Read a selector's shard, `None` if not registered
+    pub async fn shard(&self, selector: &[u8; 4]) -> Result<Option<Address>, Error> {
+        read_shard_at(self.client.clone(), &self.address, selector, self.block).await
+    }
+
+    /// ### Read a selector's shard and whether it has code
+    pub async fn shard_status(&self, selector: &[u8; 4]) -> Result<ShardStatus, Error> {
+        read_shard_status_at(self.client.clone(), &self.address, selector, self.block).await
+    }
+
+    /// ### Read a state snapshot
+    ///
+    /// #### Parameters
+    ///
+    /// - `accounts`: The accounts to read authorization for.
+    /// - `selectors`: The selectors to read shards for.
+    pub async fn state(
+        &self,
+        accounts: &[Address],
+        selectors: &[[u8; 4]],
+    ) -> Result<DeckState, Error> {
+        let block = self.block.unwrap_or(BlockNumber::Latest.into());
+        read_state(self.client.clone(), &self.address, accounts, selectors, block).await
+    }
+
+    /// ### Build an unsigned syscall with the deck's current id
+    ///
+    /// #### Parameters
+    ///
+    /// - `target`: The target.
+    /// - `value`: The call value, paid from the deck's balance.
+    /// - `deadline`: The deadline.
+    /// - `payload`: The payload.
+    pub async fn build_syscall(
+        &self,
+        target: &Address,
+        value: &U256,
+        deadline: &U64,
+        payload: &[u8],
+    ) -> Result<Syscall, Error> {
+        let id = self.id().await?;
+        Ok(Syscall::new(&id, target, value, deadline, payload, &[]))
+    }
+
+    /// ### Sign a syscall for the middleware's chain
+    ///
+    /// Signatures already on the syscall are kept; see [sign_syscall](sign::sign_syscall).
+    /// Refuses to sign unless the deck's address holds the deck's exact runtime code or a
+    /// [known version](Self::with_known_versions) at the default block.
+    pub async fn sign_syscall<S: Signer>(
+        &self,
+        syscall: &Syscall,
+        signers: &[S],
+    ) -> Result<Syscall, Error> {
+        match self.verify_code().await? {
+            Verdict::Exact | Verdict::KnownVersion(_) => {}
+            _ => return Err(Error::UnverifiedCode(self.address)),
+        }
+
+        let chain_id = self
+            .client
+            .get_chainid()
+            .await
+            .map_err(|e| Error::Middleware(e.to_string()))?;
+        sign::sign_syscall(syscall, &chain_id, signers).await
+    }
+
+    /// ### Verify a signed syscall against the latest block
+    pub async fn verify_syscall(&self, syscall: &Syscall) -> Result<SyscallReport, Error> {
+        verify::verify_syscall(self.client.clone(), &self.address, syscall).await
+    }
+
+    /// ### Dry-run a syscall's inner call at the default block, without signatures
+    pub async fn preflight_syscall(&self, syscall: &Syscall) -> Result<Preflight, Error> {
+        preflight_syscall(self.client.clone(), &self.address, syscall, self.block).await
+    }
+
+    /// ### Submit a signed syscall
+    pub async fn submit_syscall(&self, syscall: &Syscall) -> Result<TransactionReceipt, Error> {
+        self.send(encode(&Encoding::Syscall(syscall.clone()))?).await
+    }
+
+    /// ### Propose authorizing an account
+    pub async fn add_owner(&self, account: &Address, deadline: &U64) -> Result<Syscall, Error> {
+        Ok(governance::add_owner(&self.address, &self.id().await?, account, deadline))
+    }
+
+    /// ### Propose deauthorizing an account
+    pub async fn remove_owner(
+        &self,
+        account: &Address,
+        deadline: &U64,
+    ) -> Result<Syscall, Error> {
+        Ok(governance::remove_owner(&self.address, &self.id().await?, account, deadline))
+    }
+
+    /// ### Propose changing the threshold
+    pub async fn change_threshold(
+        &self,
+        threshold: usize,
+        deadline: &U64,
+    ) -> Result<Syscall, Error> {
+        governance::change_threshold(&self.address, &self.id().await?, threshold, deadline)
+    }
+
+    /// ### Propose setting a selector's shard
+    pub async fn install_shard(
+        &self,
+        selector: &[u8; 4],
+        shard: &Address,
+        deadline: &U64,
+    ) -> Result<Syscall, Error> {
+        let id = self.id().await?;
+        Ok(governance::install_shard(&self.address, &id, selector, shard, deadline))
+    }
+
+    /// ### Call a shard through the fallback at the default block
+    ///
+    /// Selectors reserved by the deck are rejected, since they never reach the fallback.
+    ///
+    /// #### Parameters
+    ///
+    /// - `selector`: The shard selector.
+    /// - `data`: The calldata following the selector.
+    pub async fn call_shard(&self, selector: &[u8; 4], data: &[u8]) -> Result<Bytes, Error> {
+        let tx = self.transaction(encode_shard_call(selector, data)?);
+
+        self.client
+            .call(&tx, self.block)
+            .await
+            .map_err(|e| decode_middleware_error(&e))
+    }
+
+    /// ### Send a shard call through the fallback
+    ///
+    /// Selectors reserved by the deck are rejected, since they never reach the fallback.
+    ///
+    /// #### Parameters
+    ///
+    /// - `selector`: The shard selector.
+    /// - `data`: The calldata following the selector.
+    pub async fn send_shard_call(
+        &self,
+        selector: &[u8; 4],
+        data: &[u8],
+    ) -> Result<TransactionReceipt, Error> {
+        self.send(encode_shard_call(selector, data)?).await
+    }
+
+    /// Builds a transaction to the deck.
+    fn transaction(&self, data: Vec<u8>) -> TypedTransaction {
+        Eip1559TransactionRequest::new()
+            .to(self.address)
+            .data(data)
+            .into()
+    }
+
+    /// Sends a transaction to the deck, decoding a revert into a typed error.
+    async fn send(&self, data: Vec<u8>) -> Result<TransactionReceipt, Error> {
+        let receipt = self
+            .client
+            .send_transaction(self.transaction(data), None)
+            .await
+            .map_err(|e| decode_middleware_error(&e))?
+            .confirmations(self.confirmations)
+            .await
+            .map_err(|e| Error::Middleware(e.to_string()))?
+            .ok_or(Error::Middleware("No receipt".to_string()))?;
+
+        match decode_receipt_revert(self.client.clone(), &receipt).await? {
+            Some(error) => Err(error),
+            None if receipt.status == Some(U64::zero()) => Err(Error::Revert(vec![])),
+            None => Ok(receipt),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::providers::{JsonRpcError, MockResponse};
+    use crate::constants::{SELECTOR_SET_AUTH, SELECTOR_SYSCALL};
+
+    fn deck() -> Address {
+        Address::repeat_byte(0xde)
+    }
+
+    #[tokio::test]
+    async fn test_reads_at_default_block() {
+        let block = BlockId::from(U64::from(10));
+        let (provider, mock) = Provider::mocked();
+        mock.push(H256::from_low_u64_be(5)).unwrap();
+
+        let deck = Deck::new(provider, &deck()).with_block(Some(block));
+
+        assert_eq!(deck.id().await, Ok(U256::from(5)));
+        mock.assert_request("eth_getStorageAt", (deck.address(), U256::zero(), block))
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_change_threshold() {
+        let (provider, mock) = Provider::mocked();
+        mock.push(H256::from_low_u64_be(3)).unwrap();
+
+        let deck = Deck::new(provider, &deck());
+        let syscall = deck.change_threshold(2, &U64::from(100)).await.unwrap();
+
+        assert_eq!(syscall.id(), &U256::from(3));
+        assert_eq!(syscall.target(), deck.address());
+        assert_eq!(syscall.value(), &U256::zero());
+        assert_eq!(syscall.payload(), crate::coder::encoder::encode_set_threshold(2));
+        assert!(syscall.signatures().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_sign_syscall_unverified_code() {
+        let (provider, mock) = Provider::mocked();
+        mock.push::<Bytes, _>(Bytes::from(vec![0x00])).unwrap();
+
+        let syscall = Syscall::new(&U256::zero(), &deck(), &U256::zero(), &U64::one(), &[], &[]);
+        let signer = "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80"
+            .parse::<LocalWallet>()
+            .unwrap();
+        let deck = Deck::new(provider, &deck());
+
+        assert_eq!(
+            deck.sign_syscall(&syscall, &[signer]).await,
+            Err(Error::UnverifiedCode(*deck.address()))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sign_syscall_known_version() {
+        let code = vec![0x00];
+        let block = BlockId::from(U64::from(10));
+        let (provider, mock) = Provider::mocked();
+        // responses are popped in reverse order
+        mock.push(U256::one()).unwrap();
+        mock.push::<Bytes, _>(Bytes::from(code.clone())).unwrap();
+
+        let syscall = Syscall::new(&U256::zero(), &deck(), &U256::zero(), &U64::one(), &[], &[]);
+        let signer = "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80"
+            .parse::<LocalWallet>()
+            .unwrap();
+        let deck = Deck::new(provider, &deck())
+            .with_block(Some(block))
+            .with_known_versions(&[H256(ethers::utils::keccak256(code))]);
+
+        let signed = deck.sign_syscall(&syscall, &[signer]).await.unwrap();
+
+        assert_eq!(signed.signatures().len(), 1);
+        mock.assert_request("eth_getCode", (deck.address(), block)).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_call_shard() {
+        let (provider, mock) = Provider::mocked();
+        mock.push::<Bytes, _>(Bytes::from(vec![0x2a])).unwrap();
+
+        let deck = Deck::new(provider, &deck());
+        let result = deck.call_shard(&[0xa9, 0x05, 0x9c, 0xbb], &[0x01]).await;
+
+        assert_eq!(result, Ok(Bytes::from(vec![0x2a])));
+
+        let tx = deck.transaction(vec![0xa9, 0x05, 0x9c, 0xbb, 0x01]);
+        mock.assert_request("eth_call", (tx, BlockId::from(BlockNumber::Latest)))
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_call_shard_not_found() {
+        let (provider, mock) = Provider::mocked();
+        mock.push_response(MockResponse::Error(JsonRpcError {
+            code: 3,
+            message: "execution reverted".to_string(),
+            data: Some(serde_json::Value::String("0xef160e8e".to_string())),
+        }));
+
+        let deck = Deck::new(provider, &deck());
+
+        assert_eq!(deck.call_shard(&[1; 4], &[]).await, Err(Error::ShardNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_shard_call_reserved_selector() {
+        let (provider, _mock) = Provider::mocked();
+        let deck = Deck::new(provider, &deck());
+
+        assert_eq!(
+            deck.call_shard(&SELECTOR_SET_AUTH, &[]).await,
+            Err(Error::ReservedSelector(SELECTOR_SET_AUTH))
+        );
+        assert_eq!(
+            deck.send_shard_call(&SELECTOR_SYSCALL, &[]).await,
+            Err(Error::ReservedSelector(SELECTOR_SYSCALL))
+        );
+    }
+}
@@ -1,14 +1,31 @@
 //! # Ether Deck Depoyer Module
 //! 
-//! Contains a deployer function for the deck.
-use crate::error::Error;
-use ethers::{providers::Middleware, types::transaction::eip2718::TypedTransaction, types::*};
+//! Contains deployer functions for the deck.
+use crate::{
+    coder::{
+        encoder::encode,
+        structures::{Encoding, SetAuth, SetShard, SetThreshold, Syscall},
+    },
+    deck::Deck,
+    error::Error,
+    history::owners::Owner,
+    safety::{checked_threshold, Projection},
+};
+use ethers::{
+    providers::Middleware, signers::Signer, types::transaction::eip2718::TypedTransaction,
+    types::*, utils::get_contract_address,
+};
 use std::borrow::Borrow;
 
 /// ## Ether Deck Bytecode
 pub static ETHER_DECK_BYTECODE: Bytes = Bytes::from_static(__BYTECODE);
 
 /// ## Deploys the Ether Deck.
+///
+/// > Note: The constructor authorizes its caller, so the deck is deployed with a plain CREATE from
+/// > the client's sender. Deploying through a CREATE2 factory would authorize the factory, which
+/// > cannot sign syscalls, leaving the deck with no usable owner. For the same address on every
+/// > chain, [deploy at a fixed nonce](deploy_at_nonce) from a dedicated deployer instead.
 pub async fn deploy<M: Middleware, B: Borrow<M>>(client: B) -> Result<Address, Error> {
     let tx: TypedTransaction = Eip1559TransactionRequest {
         to: None,
@@ -30,6 +47,266 @@ pub async fn deploy<M: Middleware, B: Borrow<M>>(client: B) -> Result<Address, E
         .ok_or(Error::Deployment("No contract address".to_string()))
 }
 
+/// ## Predicts the Ether Deck's Address.
+///
+/// A deck's address depends only on its deployer and the deployer's nonce, so a dedicated
+/// deployer key that deploys at the same nonce on every chain yields the same deck address on
+/// each of them.
+///
+/// ### Parameters
+///
+/// - `deployer`: The deployer.
+/// - `nonce`: The deployer's nonce at deployment.
+///
+/// ### Returns
+///
+/// The address [deploy_at_nonce] deploys the deck at.
+pub fn predict_address(deployer: &Address, nonce: &U256) -> Address {
+    get_contract_address(*deployer, *nonce)
+}
+
+/// ## Deploys the Ether Deck at a Fixed Nonce.
+///
+/// The deck is deployed with a plain CREATE from the client's sender, as with [deploy], only if
+/// the sender's pending nonce is `nonce`. A sender that has already used the nonce, or has yet to
+/// reach it, would deploy the deck elsewhere, so nothing is sent.
+///
+/// ### Parameters
+///
+/// - `client`: The client to deploy with. Its default sender is the deployer.
+/// - `nonce`: The deployer's nonce to deploy at.
+///
+/// ### Returns
+///
+/// Either the [predicted address](predict_address) or an [Error](crate::error::Error).
+pub async fn deploy_at_nonce<M: Middleware, B: Borrow<M>>(
+    client: B,
+    nonce: &U256,
+) -> Result<Address, Error> {
+    let client = client.borrow();
+    let deployer = client
+        .default_sender()
+        .ok_or(Error::Deployment("No default sender".to_string()))?;
+    let address = predict_address(&deployer, nonce);
+
+    let pending = client
+        .get_transaction_count(deployer, Some(BlockNumber::Pending.into()))
+        .await
+        .map_err(|e| Error::Deployment(e.to_string()))?;
+    if pending != *nonce {
+        return Err(Error::Deployment(format!(
+            "{:?} is at nonce {}, not {}",
+            deployer, pending, nonce
+        )));
+    }
+
+    let tx: TypedTransaction = Eip1559TransactionRequest {
+        from: Some(deployer),
+        to: None,
+        nonce: Some(*nonce),
+        data: Some(ETHER_DECK_BYTECODE.clone()),
+        ..Default::default()
+    }
+    .into();
+
+    let deployed = client
+        .send_transaction(tx, None)
+        .await
+        .map_err(|e| Error::Deployment(e.to_string()))?
+        .confirmations(1)
+        .await
+        .map_err(|e| Error::Deployment(e.to_string()))?
+        .ok_or(Error::Deployment("No receipt".to_string()))?
+        .contract_address
+        .ok_or(Error::Deployment("No contract address".to_string()))?;
+    if deployed != address {
+        return Err(Error::Deployment(format!(
+            "Deployed to {:?}, not {:?}",
+            deployed, address
+        )));
+    }
+
+    Ok(address)
+}
+
+/// ## Plan Deck Configuration
+///
+/// Owners are added first, then shards. The deployer signs every step alone, so the deployer is
+/// removed or the threshold is raised last; removing the deployer therefore requires a threshold
+/// of one. Each step is checked with the [lockout analyzer](crate::safety).
+///
+/// ### Parameters
+///
+/// - `deployer`: The deployer, the only owner after deployment.
+/// - `owners`: The owners to authorize.
+/// - `threshold`: The final threshold.
+/// - `shards`: The shards to install, by selector.
+/// - `remove_deployer`: Whether to deauthorize the deployer.
+///
+/// ### Returns
+///
+/// Either the governance calls, in order, or an [Error](crate::error::Error).
+pub fn plan_config(
+    deployer: &Address,
+    owners: &[Address],
+    threshold: usize,
+    shards: &[([u8; 4], Address)],
+    remove_deployer: bool,
+) -> Result<Vec<Encoding>, Error> {
+    let threshold = checked_threshold(threshold)?;
+
+    for (i, (selector, _)) in shards.iter().enumerate() {
+        if shards[..i].iter().any(|(other, _)| other == selector) {
+            return Err(Error::Deployment(format!(
+                "Duplicate shard selector 0x{}",
+                ethers::utils::hex::encode(selector)
+            )));
+        }
+    }
+    if remove_deployer && owners.contains(deployer) {
+        return Err(Error::Deployment("Deployer is listed as an owner".to_string()));
+    }
+    if remove_deployer && threshold != 1 {
+        return Err(Error::Deployment(
+            "Removing the deployer requires a threshold of 1".to_string(),
+        ));
+    }
+
+    let mut plan = owners
+        .iter()
+        .filter(|owner| *owner != deployer)
+        .map(|owner| Encoding::SetAuth(SetAuth::new(owner, true)))
+        .collect::<Vec<Encoding>>();
+    plan.extend(
+        shards
+            .iter()
+            .map(|(selector, shard)| Encoding::SetShard(SetShard::new(selector, shard))),
+    );
+    if remove_deployer {
+        plan.push(Encoding::SetAuth(SetAuth::new(deployer, false)));
+    }
+    if threshold != 1 {
+        plan.push(Encoding::SetThreshold(SetThreshold::new(threshold)));
+    }
+
+    let mut projection = Projection::new(&[Owner::new(deployer, &U64::zero())], 1);
+    for change in &plan {
+        projection = projection.apply(change)?;
+    }
+
+    Ok(plan)
+}
+
+/// ## Deploys and Configures the Ether Deck.
+///
+/// The configuration is [planned](plan_config) before deploying. Each step is signed by `signer`
+/// and submitted as a self-syscall from the client's sender. The deck makes one inner call per
+/// syscall, so the steps cannot be applied atomically. If a step fails, no further steps are
+/// submitted and a [Configuration](crate::error::Error::Configuration) error names the deck and
+/// the failing step's index in the plan; every earlier step landed, and each left the deck in a
+/// checked state.
+///
+/// ### Parameters
+///
+/// - `client`: The client to deploy and submit with.
+/// - `signer`: The deployer's key.
+/// - `owners`: The owners to authorize.
+/// - `threshold`: The final threshold.
+/// - `shards`: The shards to install, by selector.
+/// - `remove_deployer`: Whether to deauthorize the deployer.
+/// - `deadline`: The deadline of each step.
+///
+/// ### Returns
+///
+/// Either the configured deck's address or an [Error](crate::error::Error).
+pub async fn deploy_with_config<M, S>(
+    client: M,
+    signer: &S,
+    owners: &[Address],
+    threshold: usize,
+    shards: &[([u8; 4], Address)],
+    remove_deployer: bool,
+    deadline: &U64,
+) -> Result<Address, Error>
+where
+    M: Middleware + Clone + 'static,
+    S: Signer,
+{
+    let deployer = signer.address();
+    let plan = plan_config(&deployer, owners, threshold, shards, remove_deployer)?;
+
+    let address = deploy::<M, _>(client.clone()).await?;
+    let deck = Deck::new(client.clone(), &address);
+
+    if !deck.auth(&deployer).await? {
+        return Err(Error::Deployment(format!(
+            "{:?} did not deploy {:?}",
+            deployer, address
+        )));
+    }
+
+    configure(&deck, signer, &plan, deadline).await?;
+
+    let selectors = shards.iter().map(|(selector, _)| *selector).collect::<Vec<[u8; 4]>>();
+    let mut accounts = owners.to_vec();
+    accounts.push(deployer);
+    let state = deck.state(&accounts, &selectors).await?;
+
+    let configured = owners.iter().all(|owner| state.auths()[owner])
+        && state.auths()[&deployer] != remove_deployer
+        && state.threshold() as usize == threshold
+        && shards
+            .iter()
+            .all(|(selector, shard)| state.shards()[selector].unwrap_or_default() == *shard);
+
+    if !configured {
+        return Err(Error::Inconsistent(format!(
+            "{:?} is not configured as planned",
+            address
+        )));
+    }
+
+    Ok(address)
+}
+
+/// Signs and submits each step of a plan, stopping at the first failure.
+async fn configure<M, S>(
+    deck: &Deck<M>,
+    signer: &S,
+    plan: &[Encoding],
+    deadline: &U64,
+) -> Result<(), Error>
+where
+    M: Middleware + Clone + 'static,
+    S: Signer,
+{
+    for (step, change) in plan.iter().enumerate() {
+        let result = async {
+            let proposal = Syscall::new(
+                &deck.id().await?,
+                deck.address(),
+                &U256::zero(),
+                deadline,
+                &encode(change)?,
+                &[],
+            );
+            let signed = deck.sign_syscall(&proposal, std::slice::from_ref(signer)).await?;
+            deck.submit_syscall(&signed).await
+        }
+        .await;
+
+        if let Err(e) = result {
+            return Err(Error::Configuration {
+                deck: *deck.address(),
+                step,
+                error: Box::new(e),
+            });
+        }
+    }
+
+    Ok(())
+}
+
 const __BYTECODE: &[u8] = &[
     0x60, 0x01, 0x80, 0x60, 0x01, 0x55, 0x33, 0x60, 0x00, 0x52, 0x60, 0x02, 0x60, 0x20, 0x52, 0x60,
     0x40, 0x60, 0x00, 0x20, 0x55, 0x61, 0x03, 0x3d, 0x80, 0x60, 0x1f, 0x3d, 0x39, 0x3d, 0xf3, 0x60,
@@ -86,3 +363,172 @@ const __BYTECODE: &[u8] = &[
     0x58, 0x51, 0x2f, 0xae, 0x1a, 0x08, 0x8f, 0x1c, 0xef, 0xda, 0x6f, 0xf1, 0xb7, 0xe4, 0x80, 0xeb,
     0x55, 0x9e, 0x9c, 0x47, 0x00, 0xb8, 0x60, 0x00, 0x60, 0x00, 0xa3, 0x00,
 ];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        error::Violation,
+        governance::change_threshold,
+        revert::decode_revert,
+        simulate::{fixture::execute_syscall, Simulator},
+        storage::slot::auth_slot,
+    };
+    use ethers::{providers::Provider, utils::get_create2_address};
+
+    fn owner(byte: u8) -> Address {
+        Address::repeat_byte(byte)
+    }
+
+    #[test]
+    fn test_predict_address() {
+        let deployer = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266".parse::<Address>().unwrap();
+        let mut sim = Simulator::default();
+
+        assert_eq!(
+            predict_address(&deployer, &U256::zero()),
+            "0x5FbDB2315678afecb367f032d93F642f64180aa3".parse::<Address>().unwrap()
+        );
+        assert_eq!(sim.deploy(&deployer).unwrap(), predict_address(&deployer, &U256::zero()));
+        assert_eq!(sim.deploy(&deployer).unwrap(), predict_address(&deployer, &U256::one()));
+    }
+
+    #[tokio::test]
+    async fn test_deploy_at_nonce_mismatch() {
+        let (provider, mock) = Provider::mocked();
+        let provider = provider.with_sender(owner(1));
+        mock.push(U256::from(3)).unwrap();
+
+        assert!(matches!(
+            deploy_at_nonce::<Provider<_>, _>(&provider, &U256::zero()).await,
+            Err(Error::Deployment(_))
+        ));
+        mock.assert_request("eth_getTransactionCount", (owner(1), "pending")).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_factory_deployed_deck_is_unusable() {
+        // the deterministic deployment proxy's runtime code
+        const PROXY_CODE: [u8; 69] = [
+            0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+            0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+            0xff, 0xff, 0xff, 0xff, 0xe0, 0x36, 0x01, 0x60, 0x00, 0x81, 0x60, 0x20, 0x82, 0x37,
+            0x80, 0x35, 0x82, 0x82, 0x34, 0xf5, 0x80, 0x15, 0x15, 0x60, 0x39, 0x57, 0x81, 0x82,
+            0xfd, 0x5b, 0x80, 0x82, 0x52, 0x50, 0x50, 0x50, 0x60, 0x14, 0x60, 0x0c, 0xf3,
+        ];
+        let proxy = "0x4e59b44847b379578588920ca78fbf26c0b4956c".parse::<Address>().unwrap();
+        let signer = "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80"
+            .parse::<ethers::signers::LocalWallet>()
+            .unwrap();
+        let salt = H256::repeat_byte(1);
+
+        let mut sim = Simulator::new(31337);
+        sim.set_code(&proxy, &PROXY_CODE);
+        let mut data = salt.as_bytes().to_vec();
+        data.extend_from_slice(&ETHER_DECK_BYTECODE);
+        let outcome = sim.call(&signer.address(), &proxy, &U256::zero(), &data).unwrap();
+
+        let deck = Address::from_slice(outcome.output());
+        assert_eq!(deck, get_create2_address(proxy, salt, &ETHER_DECK_BYTECODE));
+        // the constructor authorizes the factory rather than the sender
+        assert_eq!(sim.storage(&deck, &auth_slot(&proxy)), H256::from_low_u64_be(1));
+        assert_eq!(sim.storage(&deck, &auth_slot(&signer.address())), H256::zero());
+
+        let proposal = change_threshold(&deck, &U256::zero(), 1, &U64::from(100)).unwrap();
+        let (_, outcome) = execute_syscall(&mut sim, &deck, &proposal, &[signer]).await;
+
+        assert_eq!(decode_revert(outcome.output()), Error::Unauthorized);
+    }
+
+    #[test]
+    fn test_plan_config_order() {
+        let shards = [([1; 4], owner(0x5d))];
+        let plan = plan_config(&owner(9), &[owner(1), owner(2)], 2, &shards, false).unwrap();
+
+        assert_eq!(
+            plan,
+            vec![
+                Encoding::SetAuth(SetAuth::new(&owner(1), true)),
+                Encoding::SetAuth(SetAuth::new(&owner(2), true)),
+                Encoding::SetShard(SetShard::new(&[1; 4], &owner(0x5d))),
+                Encoding::SetThreshold(SetThreshold::new(2)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_plan_config_remove_deployer() {
+        let plan = plan_config(&owner(9), &[owner(1)], 1, &[], true).unwrap();
+
+        assert_eq!(
+            plan,
+            vec![
+                Encoding::SetAuth(SetAuth::new(&owner(1), true)),
+                Encoding::SetAuth(SetAuth::new(&owner(9), false)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_plan_config_skips_deployer() {
+        let plan = plan_config(&owner(9), &[owner(9)], 1, &[], false).unwrap();
+
+        assert_eq!(plan, vec![]);
+    }
+
+    #[test]
+    fn test_plan_config_duplicate_selectors() {
+        let shards = [([1; 4], owner(0x5d)), ([2; 4], owner(0x5e)), ([1; 4], owner(0x5f))];
+
+        assert!(matches!(
+            plan_config(&owner(9), &[], 1, &shards, false),
+            Err(Error::Deployment(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_configure_reports_step() {
+        let (provider, mock) = Provider::mocked();
+        // responses are popped in reverse order
+        mock.push::<Bytes, _>(Bytes::from(vec![0x00])).unwrap();
+        mock.push(H256::zero()).unwrap();
+
+        let deck = Deck::new(provider, &owner(0xde));
+        let signer = "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80"
+            .parse::<ethers::signers::LocalWallet>()
+            .unwrap();
+        let plan = [Encoding::SetAuth(SetAuth::new(&owner(1), true))];
+
+        assert_eq!(
+            configure(&deck, &signer, &plan, &U64::one()).await,
+            Err(Error::Configuration {
+                deck: owner(0xde),
+                step: 0,
+                error: Box::new(Error::UnverifiedCode(owner(0xde))),
+            })
+        );
+    }
+
+    #[test]
+    fn test_plan_config_rejects_unsafe() {
+        assert!(matches!(
+            plan_config(&owner(9), &[owner(1)], 2, &[], true),
+            Err(Error::Deployment(_))
+        ));
+        assert_eq!(
+            plan_config(&owner(9), &[], 1, &[], true),
+            Err(Error::Lockout(Violation::NoOwners))
+        );
+        assert_eq!(
+            plan_config(&owner(9), &[owner(1)], 3, &[], false),
+            Err(Error::Lockout(Violation::ThresholdExceedsOwners {
+                threshold: 3,
+                owners: 2
+            }))
+        );
+        assert_eq!(
+            plan_config(&owner(9), &[owner(1)], 256, &[], false),
+            Err(Error::Lockout(Violation::ThresholdOverflow(256)))
+        );
+    }
+}
@@ -0,0 +1,153 @@
+//! # Ether Deck Digest Module
+//!
+//! Contains functions for computing the hashes a syscall's signers must sign.
+//!
+//! The deck hashes the syscall calldata from the id through the end of the payload, followed by
+//! the chain id, then wraps that hash in the EIP-191 `"\x19Ethereum Signed Message:\n32"` prefix.
+use crate::{
+    coder::{encoder::encode_syscall, structures::Syscall},
+    error::Error,
+    util::u256_to_be,
+};
+use ethers::{
+    prelude::{H256, U256},
+    utils::{hash_message, keccak256},
+};
+
+/// ## Syscall Preimage
+///
+/// ### Parameters
+///
+/// - `syscall`: The syscall to hash. Its signatures are ignored.
+/// - `chain_id`: The chain id the syscall is executed on.
+///
+/// ### Returns
+///
+/// Either the packed `calldata[4..79 + payload.len()] || chain_id` preimage or an
+/// [Error](crate::error::Error).
+pub fn syscall_preimage(syscall: &Syscall, chain_id: &U256) -> Result<Vec<u8>, Error> {
+    let mut preimage = encode_syscall(
+        syscall.id(),
+        syscall.target(),
+        syscall.value(),
+        syscall.deadline(),
+        syscall.payload(),
+        &[],
+    )?
+    .split_off(4);
+    preimage.extend_from_slice(&u256_to_be(chain_id));
+    Ok(preimage)
+}
+
+/// ## Syscall Hash
+///
+/// ### Parameters
+///
+/// - `syscall`: The syscall to hash. Its signatures are ignored.
+/// - `chain_id`: The chain id the syscall is executed on.
+///
+/// ### Returns
+///
+/// Either the raw keccak256 hash of the [syscall preimage](syscall_preimage) or an
+/// [Error](crate::error::Error).
+pub fn syscall_hash(syscall: &Syscall, chain_id: &U256) -> Result<H256, Error> {
+    Ok(H256(keccak256(syscall_preimage(syscall, chain_id)?)))
+}
+
+/// ## Syscall Digest
+///
+/// ### Parameters
+///
+/// - `syscall`: The syscall to hash. Its signatures are ignored.
+/// - `chain_id`: The chain id the syscall is executed on.
+///
+/// ### Returns
+///
+/// Either the EIP-191 prefixed [syscall hash](syscall_hash), from which the deck recovers each
+/// signer, or an [Error](crate::error::Error).
+pub fn syscall_digest(syscall: &Syscall, chain_id: &U256) -> Result<H256, Error> {
+    Ok(hash_message(syscall_hash(syscall, chain_id)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{coder::structures::DeckSignature, deploy::ETHER_DECK_BYTECODE};
+    use ethers::{
+        prelude::{Address, LocalWallet, U64},
+        signers::Signer,
+    };
+
+    fn syscall() -> Syscall {
+        Syscall::new(
+            &U256::from(9),
+            &Address::repeat_byte(0x42),
+            &U256::from(1000),
+            &U64::from(1_700_000_000u64),
+            &[0xca, 0xfe],
+            &[],
+        )
+    }
+
+    #[test]
+    fn test_syscall_preimage_layout() {
+        let preimage = syscall_preimage(&syscall(), &U256::from(1)).unwrap();
+
+        assert_eq!(preimage.len(), 75 + 2 + 32);
+        assert_eq!(U256::from_big_endian(&preimage[..32]), U256::from(9));
+        assert_eq!(preimage[71..75], [0, 0, 0, 2]);
+        assert_eq!(preimage[75..77], [0xca, 0xfe]);
+        assert_eq!(U256::from_big_endian(&preimage[77..]), U256::one());
+    }
+
+    #[test]
+    fn test_syscall_preimage_matches_bytecode() {
+        // `PUSH1 0x04; PUSH1 0x00; CALLDATACOPY; CHAINID`
+        assert!(ETHER_DECK_BYTECODE
+            .windows(6)
+            .any(|ops| ops == [0x60, 0x04, 0x60, 0x00, 0x37, 0x46]));
+        assert!(ETHER_DECK_BYTECODE
+            .windows(28)
+            .any(|bytes| bytes == b"\x19Ethereum Signed Message:\n32"));
+    }
+
+    #[test]
+    fn test_syscall_hash_ignores_signatures() {
+        let unsigned = syscall();
+        let signed = Syscall::new(
+            unsigned.id(),
+            unsigned.target(),
+            unsigned.value(),
+            unsigned.deadline(),
+            unsigned.payload(),
+            &[DeckSignature::new(27, &U256::one(), &U256::one())],
+        );
+
+        assert_eq!(
+            syscall_hash(&unsigned, &U256::one()).unwrap(),
+            syscall_hash(&signed, &U256::one()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_syscall_hash_binds_chain_id() {
+        assert_ne!(
+            syscall_hash(&syscall(), &U256::from(1)).unwrap(),
+            syscall_hash(&syscall(), &U256::from(10)).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_syscall_digest_recovers_signer() {
+        let wallet = "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80"
+            .parse::<LocalWallet>()
+            .unwrap();
+        let hash = syscall_hash(&syscall(), &U256::from(1)).unwrap();
+        let digest = syscall_digest(&syscall(), &U256::from(1)).unwrap();
+
+        let sig = wallet.sign_hash(digest).unwrap();
+
+        assert_eq!(sig.recover(digest).unwrap(), wallet.address());
+        assert_eq!(sig.recover(hash.as_bytes()).unwrap(), wallet.address());
+    }
+}
@@ -1,4 +1,5 @@
 //! # Ether Deck Error Module
+use ethers::prelude::Address;
 
 /// ## Error Enum
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -11,10 +12,69 @@ pub enum Error {
     CalldataLength,
     /// Selector not found.
     SelectorMismatch,
+    /// Selector is reserved for the deck's own dispatch.
+    ReservedSelector([u8; 4]),
     /// Calldata is malformed.
     MalformedCalldata,
+    /// Signature recovery id is not one `ecrecover` can accept.
+    InvalidRecoveryId(u64),
+    /// Init code does not match the deck's constructor layout.
+    MalformedBytecode,
+    /// Log topic not emitted by the deck.
+    UnknownEvent,
+    /// Log is malformed.
+    MalformedLog,
     /// Middleware Error.
     Middleware(String),
+    /// State replayed from logs disagrees with storage.
+    Inconsistent(String),
+    /// Merkle-Patricia proof does not verify against the trusted root.
+    InvalidProof(String),
+    /// Governance change could lock the deck.
+    Lockout(Violation),
+    /// The address does not hold the deck's runtime code.
+    UnverifiedCode(Address),
     /// Deployment Error.
     Deployment(String),
+    /// Deck configuration stopped at a step; the steps before it landed.
+    Configuration {
+        /// The deck.
+        deck: Address,
+        /// The index of the failing step in the plan.
+        step: usize,
+        /// The step's error.
+        error: Box<Error>,
+    },
+    /// Signer Error.
+    Signer(String),
+    /// Simulation Error.
+    Simulation(String),
+    /// The fallback found no shard for the selector (`0xef160e8e`).
+    ShardNotFound,
+    /// The syscall deadline has passed (`0xc6f221aa`).
+    DeadlineExpired,
+    /// A signer or caller is not authorized (`0xab8a0360`).
+    Unauthorized,
+    /// Revert data not raised by the deck, such as a syscall's bubbled inner call revert.
+    Revert(Vec<u8>),
+}
+
+/// ## Lockout Violation Enum
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Violation {
+    /// No signable owners remain.
+    NoOwners,
+    /// The threshold is zero.
+    ZeroThreshold,
+    /// The threshold is greater than the number of signable owners.
+    ThresholdExceedsOwners {
+        /// The threshold.
+        threshold: u8,
+        /// The number of signable owners.
+        owners: usize,
+    },
+    /// The threshold does not fit the deck's `u8` threshold.
+    ThresholdOverflow(usize),
+    /// The change runs code that cannot be projected, such as a shard call or a nested syscall.
+    Unverifiable,
 }
@@ -0,0 +1,266 @@
+//! # Ether Deck Events Module
+//!
+//! Contains the deck's event structures and a decoder for its logs.
+//!
+//! The deck emits each event with empty log data; every field is indexed.
+use crate::{
+    constants::{TOPIC_AUTH_SET, TOPIC_SHARD_SET, TOPIC_SYSCALL, TOPIC_THRESHOLD_SET},
+    error::Error,
+};
+use ethers::prelude::{Address, Log, H256, U256};
+
+/// ## Syscall Event
+///
+/// Emitted with the deck's id before the id is incremented.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyscallEvent {
+    id: U256,
+}
+
+impl SyscallEvent {
+    /// ### Create New Syscall Event
+    ///
+    /// #### Parameters
+    ///
+    /// - `id`: The syscall id.
+    pub fn new(id: &U256) -> Self {
+        Self { id: *id }
+    }
+
+    /// ### Get the syscall id
+    pub fn id(&self) -> &U256 {
+        &self.id
+    }
+}
+
+/// ## AuthSet Event
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthSetEvent {
+    account: Address,
+    authorized: bool,
+}
+
+impl AuthSetEvent {
+    /// ### Create New AuthSet Event
+    ///
+    /// #### Parameters
+    ///
+    /// - `account`: The account authorization was set for.
+    /// - `authorized`: Whether the account is authorized.
+    pub fn new(account: &Address, authorized: bool) -> Self {
+        Self {
+            account: *account,
+            authorized,
+        }
+    }
+
+    /// ### Get the account
+    pub fn account(&self) -> &Address {
+        &self.account
+    }
+
+    /// ### Get whether the account is authorized
+    pub fn authorized(&self) -> bool {
+        self.authorized
+    }
+}
+
+/// ## ThresholdSet Event
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThresholdSetEvent {
+    threshold: u8,
+}
+
+impl ThresholdSetEvent {
+    /// ### Create New ThresholdSet Event
+    ///
+    /// #### Parameters
+    ///
+    /// - `threshold`: The new threshold.
+    pub fn new(threshold: u8) -> Self {
+        Self { threshold }
+    }
+
+    /// ### Get the threshold
+    pub fn threshold(&self) -> u8 {
+        self.threshold
+    }
+}
+
+/// ## ShardSet Event
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShardSetEvent {
+    selector: [u8; 4],
+    shard: Address,
+}
+
+impl ShardSetEvent {
+    /// ### Create New ShardSet Event
+    ///
+    /// #### Parameters
+    ///
+    /// - `selector`: The selector the shard was set for.
+    /// - `shard`: The new shard, the zero address if removed.
+    pub fn new(selector: &[u8; 4], shard: &Address) -> Self {
+        Self {
+            selector: *selector,
+            shard: *shard,
+        }
+    }
+
+    /// ### Get the selector
+    pub fn selector(&self) -> &[u8; 4] {
+        &self.selector
+    }
+
+    /// ### Get the shard
+    pub fn shard(&self) -> &Address {
+        &self.shard
+    }
+}
+
+/// ## Deck Event Enum
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeckEvent {
+    /// Syscall Event
+    Syscall(SyscallEvent),
+    /// AuthSet Event
+    AuthSet(AuthSetEvent),
+    /// ThresholdSet Event
+    ThresholdSet(ThresholdSetEvent),
+    /// ShardSet Event
+    ShardSet(ShardSetEvent),
+}
+
+/// ## Decode Log
+///
+/// The log's address is not checked.
+///
+/// ### Parameters
+///
+/// - `log`: The log to decode.
+///
+/// ### Returns
+///
+/// Either a [DeckEvent] or an [Error](crate::error::Error).
+pub fn decode_log(log: &Log) -> Result<DeckEvent, Error> {
+    let topic0 = log.topics.first().ok_or(Error::UnknownEvent)?.to_fixed_bytes();
+    let topics = &log.topics[1..];
+
+    let event = match topic0 {
+        TOPIC_SYSCALL => {
+            let [id] = indexed(topics)?;
+            DeckEvent::Syscall(SyscallEvent::new(&U256::from(id.as_bytes())))
+        }
+        TOPIC_AUTH_SET => {
+            let [account, authorized] = indexed(topics)?;
+            DeckEvent::AuthSet(AuthSetEvent::new(
+                &Address::from(*account),
+                authorized.as_bytes()[31] == 1,
+            ))
+        }
+        TOPIC_THRESHOLD_SET => {
+            let [threshold] = indexed(topics)?;
+            DeckEvent::ThresholdSet(ThresholdSetEvent::new(threshold.as_bytes()[31]))
+        }
+        TOPIC_SHARD_SET => {
+            let [selector, shard] = indexed(topics)?;
+            let selector: [u8; 4] = selector.as_bytes()[..4]
+                .try_into()
+                .map_err(|_| Error::MalformedLog)?;
+            DeckEvent::ShardSet(ShardSetEvent::new(&selector, &Address::from(*shard)))
+        }
+        _ => return Err(Error::UnknownEvent),
+    };
+
+    if !log.data.is_empty() {
+        return Err(Error::MalformedLog);
+    }
+
+    Ok(event)
+}
+
+/// Returns the indexed topics, following `topic0`, if there are exactly `N` of them.
+fn indexed<const N: usize>(topics: &[H256]) -> Result<&[H256; N], Error> {
+    topics.try_into().map_err(|_| Error::MalformedLog)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deploy::ETHER_DECK_BYTECODE;
+
+    fn log(topics: Vec<H256>) -> Log {
+        Log {
+            topics,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_topics_in_bytecode() {
+        for topic in [TOPIC_SYSCALL, TOPIC_AUTH_SET, TOPIC_THRESHOLD_SET, TOPIC_SHARD_SET] {
+            let mut push = vec![0x7f];
+            push.extend_from_slice(&topic);
+            assert!(ETHER_DECK_BYTECODE.windows(33).any(|ops| ops == push));
+        }
+    }
+
+    #[test]
+    fn test_decode_syscall_log() {
+        let event = decode_log(&log(vec![H256(TOPIC_SYSCALL), H256::from_low_u64_be(7)]));
+
+        assert_eq!(event, Ok(DeckEvent::Syscall(SyscallEvent::new(&U256::from(7)))));
+    }
+
+    #[test]
+    fn test_decode_auth_set_log() {
+        let account = Address::repeat_byte(0xf3);
+        let event = decode_log(&log(vec![
+            H256(TOPIC_AUTH_SET),
+            H256::from(account),
+            H256::from_low_u64_be(1),
+        ]));
+
+        assert_eq!(event, Ok(DeckEvent::AuthSet(AuthSetEvent::new(&account, true))));
+    }
+
+    #[test]
+    fn test_decode_threshold_set_log() {
+        let event = decode_log(&log(vec![H256(TOPIC_THRESHOLD_SET), H256::from_low_u64_be(3)]));
+
+        assert_eq!(event, Ok(DeckEvent::ThresholdSet(ThresholdSetEvent::new(3))));
+    }
+
+    #[test]
+    fn test_decode_shard_set_log() {
+        let shard = Address::repeat_byte(0x5d);
+        let mut selector = [0u8; 32];
+        selector[..4].copy_from_slice(&[0xa9, 0x05, 0x9c, 0xbb]);
+        let event = decode_log(&log(vec![
+            H256(TOPIC_SHARD_SET),
+            H256(selector),
+            H256::from(shard),
+        ]));
+
+        assert_eq!(
+            event,
+            Ok(DeckEvent::ShardSet(ShardSetEvent::new(&[0xa9, 0x05, 0x9c, 0xbb], &shard)))
+        );
+    }
+
+    #[test]
+    fn test_decode_unknown_log() {
+        assert_eq!(decode_log(&log(vec![])), Err(Error::UnknownEvent));
+        assert_eq!(decode_log(&log(vec![H256::zero()])), Err(Error::UnknownEvent));
+    }
+
+    #[test]
+    fn test_decode_malformed_log() {
+        assert_eq!(decode_log(&log(vec![H256(TOPIC_SYSCALL)])), Err(Error::MalformedLog));
+
+        let mut with_data = log(vec![H256(TOPIC_THRESHOLD_SET), H256::zero()]);
+        with_data.data = vec![1].into();
+        assert_eq!(decode_log(&with_data), Err(Error::MalformedLog));
+    }
+}
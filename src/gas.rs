@@ -0,0 +1,446 @@
+//! # Ether Deck Gas Module
+//!
+//! Contains a model predicting the gas a successful syscall uses.
+//!
+//! The model follows the deck's syscall path: the fixed cost of its instructions, a cold `SLOAD`
+//! of the threshold and id, one `ecrecover` precompile call and cold `SLOAD` of an auth slot per
+//! signature read, hashing and memory for the payload, the `Syscall` `LOG2`, the id's `SSTORE`,
+//! and the inner call. The inner call's own gas is an input, such as from a
+//! [preflight](crate::preflight::preflight_syscall).
+//!
+//! The rules the model follows are chosen with a [Fork]. Opcode and precompile costs are the same
+//! for Cancun and Prague; Prague adds the EIP-7623 calldata floor and the BLS12-381 precompiles.
+use crate::{
+    coder::{
+        encoder::encode,
+        structures::{Encoding, Syscall},
+    },
+    error::Error,
+    preflight::Preflight,
+};
+use ethers::prelude::Address;
+
+/// Fixed cost of the syscall path's instructions, excluding the signature loop.
+///
+/// `SLOAD`, `LOG2`, `SSTORE`, and the calls are counted by their own constants, as are the word
+/// and memory costs of copies and hashes. Each region's remaining opcodes, by runtime offset:
+///
+/// - dispatch, `0x00`-`0x0d`: 4 `PUSH`, `CALLDATALOAD`, `SHR`, `DUP`, `EQ` at 3 and `JUMPI` at
+///   10, for 34.
+/// - deadline, `0x8a`-`0x96`: `JUMPDEST` at 1, 3 `PUSH`, `CALLDATALOAD`, `SHR`, `LT` at 3,
+///   `TIMESTAMP` at 2 and `JUMPI` at 10, for 31.
+/// - digest, `0xc0`-`0x11b`: `JUMPDEST` at 1, 18 `PUSH`, 5 `DUP`, 4 `SWAP`, 4 `ADD`, 4 `MSTORE`,
+///   `CALLDATALOAD`, `SHR`, `CALLDATACOPY` at 3, `CHAINID` at 2, `MUL` at 5 and 2 `KECCAK256` at
+///   30, for 182.
+/// - loop exit, `0x15e`-`0x161`: `PUSH` at 3 and `JUMPI` at 10, for 13.
+/// - syscall log, `0x18b`-`0x1be`: `JUMPDEST` at 1, 3 `POP` at 2, 6 `PUSH`, `DUP`, `ADD` at 3,
+///   for 31.
+/// - inner call, `0x1bf`-`0x1e3`: 12 `PUSH`, 2 `CALLDATALOAD`, 2 `SHR`, `DUP`, `SWAP`,
+///   `CALLDATACOPY`, `RETURNDATACOPY` at 3, `GAS`, `RETURNDATASIZE` at 2 and `JUMPI` at 10, for
+///   74.
+/// - return, `0x1e8`-`0x1ec`: `JUMPDEST` at 1, `RETURNDATASIZE` at 2 and `PUSH` at 3, for 6.
+const SYSCALL_BASE: u64 = 34 + 31 + 182 + 13 + 31 + 74 + 6;
+/// Fixed cost of one signature loop iteration, `0x11d`-`0x15d`.
+///
+/// `JUMPDEST` at 1; 17 `PUSH`, 7 `DUP`, 3 `SWAP`, 3 `MSTORE`, 2 `ADD`, 2 `AND`, `CALLDATALOAD`,
+/// `SHR`, `CALLDATACOPY`, `MLOAD`, `GT`, `LT` at 3; `GAS`, `POP` at 2; `KECCAK256` at 30 and
+/// `JUMPI` at 10, for 165. The `STATICCALL` and `SLOAD` are counted by `ECRECOVER` and
+/// `COLD_SLOAD`.
+const SIGNATURE_BASE: u64 = 1 + 40 * 3 + 2 * 2 + 30 + 10;
+/// `ecrecover` precompile call, warm.
+const ECRECOVER: u64 = 100 + 3000;
+/// Cold `SLOAD`.
+const COLD_SLOAD: u64 = 2100;
+/// `SSTORE` of a warm slot from zero.
+const SSTORE_SET: u64 = 20000;
+/// `SSTORE` of a warm, nonzero slot.
+const SSTORE_RESET: u64 = 2900;
+/// `LOG2` without data.
+const LOG2: u64 = 375 + 2 * 375;
+/// Account access, warm.
+const WARM_ACCESS: u64 = 100;
+/// Account access, cold.
+const COLD_ACCESS: u64 = 2600;
+/// Value transfer, less the 2,300 gas stipend the callee receives for free.
+const CALL_VALUE: u64 = 9000 - 2300;
+/// Value transfer to an empty account.
+const NEW_ACCOUNT: u64 = 25000;
+/// Transaction intrinsic gas.
+const TX_BASE: u64 = 21000;
+/// Calldata zero byte.
+const TX_DATA_ZERO: u64 = 4;
+/// Calldata nonzero byte.
+const TX_DATA_NONZERO: u64 = 16;
+/// Calldata floor cost per token, EIP-7623, from Prague.
+const TX_FLOOR_PER_TOKEN: u64 = 10;
+/// Bytes the deck hashes besides the payload, from the id through the payload length plus the
+/// chain id.
+const HASH_OVERHEAD: usize = 75 + 32;
+/// Memory the signature loop uses, in words.
+const SIGNATURE_MEMORY_WORDS: u64 = 6;
+
+/// ## Fork Enum
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fork {
+    /// Cancun, with precompiles `0x01` through `0x0a`.
+    Cancun,
+    /// Prague, adding the EIP-7623 calldata floor and the EIP-2537 precompiles through `0x11`.
+    Prague,
+}
+
+impl Fork {
+    /// Returns the highest precompile address, warm in every transaction.
+    fn precompile_max(&self) -> u8 {
+        match self {
+            Fork::Cancun => 0x0a,
+            Fork::Prague => 0x11,
+        }
+    }
+}
+
+/// ## Inner Call
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InnerCall {
+    gas: u64,
+    return_len: usize,
+    new_account: bool,
+}
+
+impl InnerCall {
+    /// ### Create New Inner Call
+    ///
+    /// #### Parameters
+    ///
+    /// - `gas`: The gas the target uses.
+    /// - `return_len`: The length of the target's return data.
+    pub fn new(gas: u64, return_len: usize) -> Self {
+        Self {
+            gas,
+            return_len,
+            new_account: false,
+        }
+    }
+
+    /// ### Set whether the target is an empty account, charged for value transfers
+    pub fn with_new_account(mut self, new_account: bool) -> Self {
+        self.new_account = new_account;
+        self
+    }
+
+    /// ### Get the gas the target uses
+    pub fn gas(&self) -> u64 {
+        self.gas
+    }
+
+    /// ### Get the length of the target's return data
+    pub fn return_len(&self) -> usize {
+        self.return_len
+    }
+
+    /// ### Get whether the target is an empty account
+    pub fn is_new_account(&self) -> bool {
+        self.new_account
+    }
+}
+
+impl TryFrom<&Preflight> for InnerCall {
+    type Error = Error;
+
+    /// Fails if the pre-flight could not be traced for its gas.
+    fn try_from(preflight: &Preflight) -> Result<Self, Error> {
+        let gas = preflight
+            .gas_used()
+            .ok_or(Error::Middleware("Pre-flight has no gas".to_string()))?;
+        Ok(Self::new(gas.low_u64(), preflight.output().len()))
+    }
+}
+
+/// ## Gas Estimate
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GasEstimate {
+    intrinsic: u64,
+    floor: u64,
+    execution: u64,
+    inner: u64,
+}
+
+impl GasEstimate {
+    /// ### Get the intrinsic gas, including calldata
+    pub fn intrinsic(&self) -> u64 {
+        self.intrinsic
+    }
+
+    /// ### Get the EIP-7623 calldata floor, zero before Prague
+    pub fn floor(&self) -> u64 {
+        self.floor
+    }
+
+    /// ### Get the deck's execution gas, excluding the target's
+    pub fn execution(&self) -> u64 {
+        self.execution
+    }
+
+    /// ### Get the target's gas
+    pub fn inner(&self) -> u64 {
+        self.inner
+    }
+
+    /// ### Get the gas used
+    pub fn gas_used(&self) -> u64 {
+        (self.intrinsic + self.execution + self.inner).max(self.floor)
+    }
+
+    /// ### Get a sufficient gas limit
+    ///
+    /// The deck forwards all but one 64th of its remaining gas to the target, so the limit
+    /// reserves a 63rd of the target's gas on top of the gas used.
+    pub fn gas_limit(&self) -> u64 {
+        (self.intrinsic + self.execution + self.inner + self.inner.div_ceil(63)).max(self.floor)
+    }
+}
+
+/// ## Estimate Syscall Gas
+///
+/// Assumes the syscall succeeds and that its id is the deck's id, so a zero id is the deck's
+/// first syscall and its `SSTORE` sets the id slot from zero.
+///
+/// ### Parameters
+///
+/// - `deck`: The deck's address.
+/// - `syscall`: The signed syscall.
+/// - `threshold`: The deck's threshold; the deck reads at least one signature.
+/// - `inner`: The target's call.
+/// - `fork`: The fork whose rules apply.
+///
+/// ### Returns
+///
+/// Either the [GasEstimate] or an [Error](crate::error::Error) if the syscall cannot be encoded.
+pub fn estimate_syscall_gas(
+    deck: &Address,
+    syscall: &Syscall,
+    threshold: u8,
+    inner: &InnerCall,
+    fork: Fork,
+) -> Result<GasEstimate, Error> {
+    let calldata = encode(&Encoding::Syscall(syscall.clone()))?;
+    let zeros = calldata.iter().filter(|byte| **byte == 0).count() as u64;
+    let nonzeros = calldata.len() as u64 - zeros;
+
+    let intrinsic = TX_BASE + zeros * TX_DATA_ZERO + nonzeros * TX_DATA_NONZERO;
+    let floor = match fork {
+        Fork::Cancun => 0,
+        Fork::Prague => TX_BASE + (zeros + 4 * nonzeros) * TX_FLOOR_PER_TOKEN,
+    };
+
+    let payload_len = syscall.payload().len();
+    let signatures = threshold.max(1) as u64;
+    let memory = words(payload_len + HASH_OVERHEAD)
+        .max(SIGNATURE_MEMORY_WORDS)
+        .max(words(inner.return_len));
+
+    let mut execution = SYSCALL_BASE
+        + 2 * COLD_SLOAD
+        + signatures * (SIGNATURE_BASE + ECRECOVER + COLD_SLOAD + copy(64) + hash(64))
+        + copy(payload_len + HASH_OVERHEAD - 32)
+        + hash(payload_len + HASH_OVERHEAD)
+        + hash(60)
+        + memory_cost(memory)
+        + LOG2
+        + copy(payload_len)
+        + copy(inner.return_len);
+
+    execution += match syscall.id().is_zero() {
+        true => SSTORE_SET,
+        false => SSTORE_RESET,
+    };
+    execution += match is_warm(deck, syscall.target(), fork) {
+        true => WARM_ACCESS,
+        false => COLD_ACCESS,
+    };
+    if !syscall.value().is_zero() {
+        execution += CALL_VALUE;
+        if inner.new_account {
+            execution += NEW_ACCOUNT;
+        }
+    }
+
+    Ok(GasEstimate {
+        intrinsic,
+        floor,
+        execution,
+        inner: inner.gas,
+    })
+}
+
+/// Returns whether the target is warm when the deck calls it.
+fn is_warm(deck: &Address, target: &Address, fork: Fork) -> bool {
+    let precompile = target[..19].iter().all(|byte| *byte == 0)
+        && (1..=fork.precompile_max()).contains(&target[19]);
+    target == deck || precompile
+}
+
+/// Returns the number of words covering a length.
+fn words(len: usize) -> u64 {
+    len.div_ceil(32) as u64
+}
+
+/// Returns the word cost of copying a length.
+fn copy(len: usize) -> u64 {
+    3 * words(len)
+}
+
+/// Returns the word cost of hashing a length.
+fn hash(len: usize) -> u64 {
+    6 * words(len)
+}
+
+/// Returns the cost of expanding memory to a number of words.
+fn memory_cost(words: u64) -> u64 {
+    3 * words + words * words / 512
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        simulate::{
+            fixture::{deck_with_owners, execute_syscall, RECORDER},
+            Simulator,
+        },
+        util::u256_to_be,
+    };
+    use ethers::{
+        prelude::{U256, U64},
+        signers::{LocalWallet, Signer},
+    };
+
+    /// Returns the gas the recorder uses for a payload length.
+    fn recorder_gas(len: usize) -> u64 {
+        2 + 3 + 3 + 3 + copy(len) + memory_cost(words(len)) + 2 + 3 + 375 + 8 * len as u64
+    }
+
+    /// Signs and executes a syscall on a deck with the given number of owners, returning the
+    /// signed syscall and the simulated gas used.
+    async fn simulate(
+        owners: u8,
+        id: u64,
+        target: &Address,
+        value: u64,
+        payload: &[u8],
+        code: &[u8],
+    ) -> (Address, Syscall, u64) {
+        let mut sim = Simulator::new(31337);
+        let wallets = (1..=owners as u64)
+            .map(|key| LocalWallet::from_bytes(&u256_to_be(&U256::from(key))).unwrap())
+            .collect::<Vec<LocalWallet>>();
+        let addresses = wallets.iter().map(Signer::address).collect::<Vec<Address>>();
+        let deck = deck_with_owners(
+            &mut sim,
+            &Address::repeat_byte(9),
+            &addresses,
+            owners,
+            &U256::from(id),
+        );
+        sim.set_balance(&deck, &U256::from(value));
+        if !code.is_empty() {
+            sim.set_code(target, code);
+        }
+
+        let unsigned = Syscall::new(
+            &U256::from(id),
+            target,
+            &U256::from(value),
+            &U64::from(100),
+            payload,
+            &[],
+        );
+        let (signed, outcome) = execute_syscall(&mut sim, &deck, &unsigned, &wallets).await;
+
+        assert!(outcome.is_success(), "{:?}", outcome);
+        (deck, signed, outcome.gas_used())
+    }
+
+    #[tokio::test]
+    async fn test_estimate_matches_simulator() {
+        let target = Address::repeat_byte(0x7a);
+
+        for (owners, payload_len) in [(1, 0), (1, 1), (2, 32), (3, 100), (4, 257)] {
+            let payload = vec![0xab; payload_len];
+            for id in [0, 5] {
+                let (deck, syscall, used) = simulate(owners, id, &target, 0, &payload, &[]).await;
+                let inner = InnerCall::new(0, 0);
+                let estimate =
+                    estimate_syscall_gas(&deck, &syscall, owners, &inner, Fork::Cancun).unwrap();
+
+                assert_eq!(estimate.gas_used(), used, "{} owners, {} bytes", owners, payload_len);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_estimate_value_to_new_account() {
+        let target = Address::repeat_byte(0x7a);
+        let (deck, syscall, used) = simulate(1, 1, &target, 1, &[], &[]).await;
+        let inner = InnerCall::new(0, 0).with_new_account(true);
+
+        let estimate = estimate_syscall_gas(&deck, &syscall, 1, &inner, Fork::Cancun).unwrap();
+
+        assert_eq!(estimate.gas_used(), used);
+    }
+
+    #[tokio::test]
+    async fn test_estimate_with_target_code() {
+        let target = Address::repeat_byte(0x7a);
+        let payload = [0xcd; 40];
+        let (deck, syscall, used) = simulate(2, 1, &target, 0, &payload, &RECORDER).await;
+        let inner = InnerCall::new(recorder_gas(payload.len()), 0);
+
+        let estimate = estimate_syscall_gas(&deck, &syscall, 2, &inner, Fork::Cancun).unwrap();
+        assert_eq!(estimate.gas_used(), used);
+        assert!(estimate.gas_limit() > estimate.gas_used());
+    }
+
+    #[test]
+    fn test_warm_targets() {
+        let deck = Address::repeat_byte(0xde);
+
+        assert!(is_warm(&deck, &deck, Fork::Cancun));
+        assert!(is_warm(&deck, &Address::from_low_u64_be(1), Fork::Cancun));
+        assert!(!is_warm(&deck, &Address::zero(), Fork::Cancun));
+        assert!(!is_warm(&deck, &Address::repeat_byte(1), Fork::Cancun));
+        // BLS12-381 precompiles
+        assert!(!is_warm(&deck, &Address::from_low_u64_be(0x0b), Fork::Cancun));
+        assert!(is_warm(&deck, &Address::from_low_u64_be(0x0b), Fork::Prague));
+        assert!(is_warm(&deck, &Address::from_low_u64_be(0x11), Fork::Prague));
+        assert!(!is_warm(&deck, &Address::from_low_u64_be(0x12), Fork::Prague));
+    }
+
+    #[tokio::test]
+    async fn test_calldata_floor() {
+        let target = Address::repeat_byte(0x7a);
+        let payload = [0xff; 8192];
+        let (deck, syscall, used) = simulate(1, 1, &target, 0, &payload, &[]).await;
+        let inner = InnerCall::new(0, 0);
+
+        // the simulator runs Cancun, which has no floor
+        let cancun = estimate_syscall_gas(&deck, &syscall, 1, &inner, Fork::Cancun).unwrap();
+        assert_eq!(cancun.floor(), 0);
+        assert_eq!(cancun.gas_used(), used);
+
+        // the unsigned calldata has 55 zero bytes: 3 in the selector, 31 in the id, 11 in the
+        // value, 7 in the deadline and 3 in the payload length; the other 8,216 are nonzero
+        let unsigned = Syscall::new(
+            syscall.id(),
+            syscall.target(),
+            syscall.value(),
+            syscall.deadline(),
+            syscall.payload(),
+            &[],
+        );
+        let prague = estimate_syscall_gas(&deck, &unsigned, 1, &inner, Fork::Prague).unwrap();
+        assert_eq!(prague.intrinsic(), 21_000 + 55 * 4 + 8_216 * 16);
+        assert_eq!(prague.floor(), 21_000 + (55 + 8_216 * 4) * 10);
+        assert_eq!(prague.gas_used(), prague.floor());
+        assert!(prague.floor() > prague.intrinsic() + prague.execution());
+    }
+}
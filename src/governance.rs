@@ -0,0 +1,175 @@
+//! # Ether Deck Governance Module
+//!
+//! Contains builders for governance proposals.
+//!
+//! `setAuth`, `setThreshold`, and `setShard` revert unless the caller is the deck, so each is
+//! proposed as a syscall targeting the deck itself with zero value. Proposals are unsigned; sign
+//! them with [sign_syscall](crate::sign::sign_syscall) and encode them with
+//! [encode_syscall](crate::coder::encoder::encode_syscall).
+use crate::{
+    coder::{
+        encoder::{encode_set_auth, encode_set_shard, encode_set_threshold},
+        structures::Syscall,
+    },
+    error::Error,
+    safety::checked_threshold,
+};
+use ethers::prelude::{Address, U256, U64};
+
+/// ## Add Owner
+///
+/// ### Parameters
+///
+/// - `deck`: The deck's address.
+/// - `id`: The deck's syscall id.
+/// - `account`: The account to authorize.
+/// - `deadline`: The deadline.
+///
+/// ### Returns
+///
+/// The unsigned proposal.
+pub fn add_owner(deck: &Address, id: &U256, account: &Address, deadline: &U64) -> Syscall {
+    proposal(deck, id, deadline, &encode_set_auth(account, true))
+}
+
+/// ## Remove Owner
+///
+/// ### Parameters
+///
+/// - `deck`: The deck's address.
+/// - `id`: The deck's syscall id.
+/// - `account`: The account to deauthorize.
+/// - `deadline`: The deadline.
+///
+/// ### Returns
+///
+/// The unsigned proposal.
+pub fn remove_owner(deck: &Address, id: &U256, account: &Address, deadline: &U64) -> Syscall {
+    proposal(deck, id, deadline, &encode_set_auth(account, false))
+}
+
+/// ## Change Threshold
+///
+/// ### Parameters
+///
+/// - `deck`: The deck's address.
+/// - `id`: The deck's syscall id.
+/// - `threshold`: The new threshold.
+/// - `deadline`: The deadline.
+///
+/// ### Returns
+///
+/// Either the unsigned proposal or a [Lockout](crate::error::Error::Lockout) error if the
+/// threshold does not fit the deck's `u8` threshold.
+pub fn change_threshold(
+    deck: &Address,
+    id: &U256,
+    threshold: usize,
+    deadline: &U64,
+) -> Result<Syscall, Error> {
+    let threshold = checked_threshold(threshold)?;
+    Ok(proposal(deck, id, deadline, &encode_set_threshold(threshold)))
+}
+
+/// ## Install Shard
+///
+/// ### Parameters
+///
+/// - `deck`: The deck's address.
+/// - `id`: The deck's syscall id.
+/// - `selector`: The selector to route to the shard.
+/// - `shard`: The shard, the zero address to remove it.
+/// - `deadline`: The deadline.
+///
+/// ### Returns
+///
+/// The unsigned proposal.
+pub fn install_shard(
+    deck: &Address,
+    id: &U256,
+    selector: &[u8; 4],
+    shard: &Address,
+    deadline: &U64,
+) -> Syscall {
+    proposal(deck, id, deadline, &encode_set_shard(selector, shard))
+}
+
+/// Wraps a governance call in a zero value syscall to the deck.
+fn proposal(deck: &Address, id: &U256, deadline: &U64, payload: &[u8]) -> Syscall {
+    Syscall::new(id, deck, &U256::zero(), deadline, payload, &[])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        coder::{
+            decoder::decode,
+            encoder::encode_syscall,
+            structures::{Encoding, SetAuth, SetShard, SetThreshold},
+        },
+        error::Violation,
+    };
+
+    /// Encodes the proposal's outer call and decodes its inner call.
+    fn inner(proposal: &Syscall) -> Encoding {
+        let calldata = encode_syscall(
+            proposal.id(),
+            proposal.target(),
+            proposal.value(),
+            proposal.deadline(),
+            proposal.payload(),
+            proposal.signatures(),
+        )
+        .unwrap();
+
+        match decode(&calldata).unwrap() {
+            Encoding::Syscall(outer) => {
+                assert_eq!(&outer, proposal);
+                decode(outer.payload()).unwrap()
+            }
+            _ => panic!("expected syscall"),
+        }
+    }
+
+    #[test]
+    fn test_add_and_remove_owner() {
+        let deck = Address::repeat_byte(0xde);
+        let account = Address::repeat_byte(1);
+
+        let add = add_owner(&deck, &U256::from(7), &account, &U64::from(100));
+        assert_eq!(add.id(), &U256::from(7));
+        assert_eq!(add.target(), &deck);
+        assert_eq!(add.value(), &U256::zero());
+        assert_eq!(add.deadline(), &U64::from(100));
+        assert_eq!(inner(&add), Encoding::SetAuth(SetAuth::new(&account, true)));
+
+        let remove = remove_owner(&deck, &U256::from(8), &account, &U64::from(100));
+        assert_eq!(inner(&remove), Encoding::SetAuth(SetAuth::new(&account, false)));
+    }
+
+    #[test]
+    fn test_change_threshold() {
+        let proposal =
+            change_threshold(&Address::zero(), &U256::zero(), 3, &U64::from(100)).unwrap();
+
+        assert_eq!(inner(&proposal), Encoding::SetThreshold(SetThreshold::new(3)));
+    }
+
+    #[test]
+    fn test_change_threshold_overflow() {
+        assert_eq!(
+            change_threshold(&Address::zero(), &U256::zero(), 256, &U64::from(100)),
+            Err(Error::Lockout(Violation::ThresholdOverflow(256)))
+        );
+    }
+
+    #[test]
+    fn test_install_shard() {
+        let shard = Address::repeat_byte(0x5d);
+        let proposal =
+            install_shard(&Address::zero(), &U256::zero(), &[1; 4], &shard, &U64::from(100));
+
+        assert_eq!(inner(&proposal), Encoding::SetShard(SetShard::new(&[1; 4], &shard)));
+    }
+}
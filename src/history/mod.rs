@@ -0,0 +1,2 @@
+pub mod owners;
+pub mod shards;
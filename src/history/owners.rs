@@ -0,0 +1,308 @@
+//! # Ether Deck Owner History Module
+//!
+//! Contains functions for enumerating the deck's owners by replaying its `AuthSet` logs.
+//!
+//! The authorization mapping cannot be enumerated from storage. The deployer is authorized by the
+//! constructor without an event, so it is included from the deployment block.
+use crate::{
+    constants::TOPIC_AUTH_SET,
+    error::Error,
+    events::{decode_log, DeckEvent},
+    storage::reader::read_auth_at,
+};
+use ethers::prelude::*;
+use std::collections::BTreeMap;
+
+/// ## Owner
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Owner {
+    account: Address,
+    added_at: U64,
+}
+
+impl Owner {
+    /// ### Create New Owner
+    ///
+    /// #### Parameters
+    ///
+    /// - `account`: The owner's account.
+    /// - `added_at`: The block the owner was authorized in.
+    pub fn new(account: &Address, added_at: &U64) -> Self {
+        Self {
+            account: *account,
+            added_at: *added_at,
+        }
+    }
+
+    /// ### Get the account
+    pub fn account(&self) -> &Address {
+        &self.account
+    }
+
+    /// ### Get the block the owner was authorized in
+    pub fn added_at(&self) -> &U64 {
+        &self.added_at
+    }
+}
+
+/// ## Find Deployer
+///
+/// ### Parameters
+///
+/// - `provider`: The provider to read the chain with.
+/// - `deck`: The deck's address.
+/// - `deployed_at`: The deck's deployment block.
+///
+/// ### Returns
+///
+/// Either the sender of the transaction that created the deck or an
+/// [Error](crate::error::Error). Decks created by a factory contract are not found.
+pub async fn find_deployer<M>(
+    provider: M,
+    deck: &Address,
+    deployed_at: &U64,
+) -> Result<Address, Error>
+where
+    M: Middleware + 'static,
+{
+    let block = provider
+        .get_block_with_txs(*deployed_at)
+        .await
+        .map_err(|e| Error::Middleware(e.to_string()))?
+        .ok_or(Error::Middleware("No deployment block".to_string()))?;
+
+    for tx in block.transactions.iter().filter(|tx| tx.to.is_none()) {
+        let receipt = provider
+            .get_transaction_receipt(tx.hash)
+            .await
+            .map_err(|e| Error::Middleware(e.to_string()))?;
+
+        if receipt.and_then(|receipt| receipt.contract_address) == Some(*deck) {
+            return Ok(tx.from);
+        }
+    }
+
+    Err(Error::Deployment("No deployment transaction".to_string()))
+}
+
+/// ## Replay AuthSet Logs
+///
+/// ### Parameters
+///
+/// - `deployer`: The deck's deployer.
+/// - `deployed_at`: The deck's deployment block.
+/// - `logs`: The deck's `AuthSet` logs, in any order.
+///
+/// ### Returns
+///
+/// Either the owners, ordered by address, or an [Error](crate::error::Error) if a log is not an
+/// `AuthSet` log.
+pub fn replay_auth_logs(
+    deployer: &Address,
+    deployed_at: &U64,
+    logs: &[Log],
+) -> Result<Vec<Owner>, Error> {
+    let mut logs = logs.iter().collect::<Vec<&Log>>();
+    logs.sort_by_key(|log| (log.block_number, log.log_index));
+
+    let mut owners = BTreeMap::from([(*deployer, *deployed_at)]);
+
+    for log in logs {
+        let event = match decode_log(log)? {
+            DeckEvent::AuthSet(event) => event,
+            _ => return Err(Error::MalformedLog),
+        };
+
+        if event.authorized() {
+            let block = log.block_number.ok_or(Error::MalformedLog)?;
+            owners.entry(*event.account()).or_insert(block);
+        } else {
+            owners.remove(event.account());
+        }
+    }
+
+    Ok(owners
+        .iter()
+        .map(|(account, added_at)| Owner::new(account, added_at))
+        .collect())
+}
+
+/// ## Read Owners
+///
+/// Each replayed owner is checked against the deck's authorization storage at `block`.
+///
+/// ### Parameters
+///
+/// - `provider`: The provider to read the chain with.
+/// - `deck`: The deck's address.
+/// - `deployed_at`: The deck's deployment block.
+/// - `block`: The block to enumerate the owners at.
+///
+/// ### Returns
+///
+/// Either the owners, ordered by address, or an [Error](crate::error::Error).
+pub async fn read_owners<M>(
+    provider: M,
+    deck: &Address,
+    deployed_at: &U64,
+    block: &U64,
+) -> Result<Vec<Owner>, Error>
+where
+    M: Middleware + Clone + 'static,
+{
+    let deployer = find_deployer(provider.clone(), deck, deployed_at).await?;
+
+    let filter = Filter::new()
+        .address(*deck)
+        .topic0(H256(TOPIC_AUTH_SET))
+        .from_block(*deployed_at)
+        .to_block(*block);
+    let logs = provider
+        .get_logs(&filter)
+        .await
+        .map_err(|e| Error::Middleware(e.to_string()))?;
+
+    let owners = replay_auth_logs(&deployer, deployed_at, &logs)?;
+
+    for owner in &owners {
+        if !read_auth_at(provider.clone(), deck, owner.account(), Some((*block).into())).await? {
+            return Err(Error::Inconsistent(format!(
+                "{:?} is not authorized at block {}",
+                owner.account(),
+                block
+            )));
+        }
+    }
+
+    Ok(owners)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn auth_log(account: u8, authorized: bool, block: u64, index: u64) -> Log {
+        Log {
+            topics: vec![
+                H256(TOPIC_AUTH_SET),
+                H256::from(Address::repeat_byte(account)),
+                H256::from_low_u64_be(authorized as u64),
+            ],
+            block_number: Some(U64::from(block)),
+            log_index: Some(U256::from(index)),
+            ..Default::default()
+        }
+    }
+
+    fn accounts(owners: &[Owner]) -> Vec<Address> {
+        owners.iter().map(|owner| *owner.account()).collect()
+    }
+
+    #[test]
+    fn test_replay_deployer_only() {
+        let owners = replay_auth_logs(&Address::repeat_byte(9), &U64::from(1), &[]).unwrap();
+
+        assert_eq!(owners, vec![Owner::new(&Address::repeat_byte(9), &U64::from(1))]);
+    }
+
+    #[test]
+    fn test_replay_add_and_remove() {
+        let logs = [
+            auth_log(1, true, 2, 0),
+            auth_log(2, true, 2, 1),
+            auth_log(9, false, 3, 0),
+            auth_log(2, false, 4, 0),
+        ];
+        let owners = replay_auth_logs(&Address::repeat_byte(9), &U64::from(1), &logs).unwrap();
+
+        assert_eq!(owners, vec![Owner::new(&Address::repeat_byte(1), &U64::from(2))]);
+    }
+
+    #[test]
+    fn test_replay_orders_logs() {
+        // removed then re-added; unordered input
+        let logs = [auth_log(1, true, 5, 0), auth_log(1, false, 3, 0), auth_log(1, true, 2, 0)];
+        let owners = replay_auth_logs(&Address::repeat_byte(9), &U64::from(1), &logs).unwrap();
+
+        assert_eq!(
+            accounts(&owners),
+            vec![Address::repeat_byte(1), Address::repeat_byte(9)]
+        );
+        assert_eq!(owners[0].added_at(), &U64::from(5));
+    }
+
+    #[test]
+    fn test_replay_keeps_first_authorization() {
+        let logs = [auth_log(1, true, 2, 0), auth_log(1, true, 3, 0)];
+        let owners = replay_auth_logs(&Address::repeat_byte(9), &U64::from(1), &logs).unwrap();
+
+        assert_eq!(owners[0].added_at(), &U64::from(2));
+    }
+
+    #[test]
+    fn test_replay_rejects_other_logs() {
+        let mut log = auth_log(1, true, 2, 0);
+        log.topics = vec![H256(crate::constants::TOPIC_THRESHOLD_SET), H256::zero()];
+
+        assert_eq!(
+            replay_auth_logs(&Address::zero(), &U64::zero(), &[log]),
+            Err(Error::MalformedLog)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_read_owners() {
+        let deck = Address::repeat_byte(0xde);
+        let deployer = Address::repeat_byte(9);
+        let deployment = Transaction {
+            from: deployer,
+            to: None,
+            ..Default::default()
+        };
+        let block = Block::<Transaction> {
+            transactions: vec![deployment],
+            ..Default::default()
+        };
+        let receipt = TransactionReceipt {
+            contract_address: Some(deck),
+            ..Default::default()
+        };
+
+        let (provider, mock) = Provider::mocked();
+        // responses are popped in reverse order
+        mock.push(H256::from_low_u64_be(1)).unwrap();
+        mock.push(H256::from_low_u64_be(1)).unwrap();
+        mock.push::<Vec<Log>, _>(vec![auth_log(1, true, 2, 0)]).unwrap();
+        mock.push(receipt).unwrap();
+        mock.push(block).unwrap();
+
+        let owners = read_owners(provider, &deck, &U64::from(1), &U64::from(10))
+            .await
+            .unwrap();
+
+        assert_eq!(accounts(&owners), vec![Address::repeat_byte(1), deployer]);
+    }
+
+    #[tokio::test]
+    async fn test_read_owners_inconsistent() {
+        let deck = Address::repeat_byte(0xde);
+        let block = Block::<Transaction> {
+            transactions: vec![Transaction::default()],
+            ..Default::default()
+        };
+        let receipt = TransactionReceipt {
+            contract_address: Some(deck),
+            ..Default::default()
+        };
+
+        let (provider, mock) = Provider::mocked();
+        mock.push(H256::zero()).unwrap();
+        mock.push::<Vec<Log>, _>(vec![]).unwrap();
+        mock.push(receipt).unwrap();
+        mock.push(block).unwrap();
+
+        let result = read_owners(provider, &deck, &U64::from(1), &U64::from(10)).await;
+
+        assert!(matches!(result, Err(Error::Inconsistent(_))));
+    }
+}
@@ -0,0 +1,287 @@
+//! # Ether Deck Shard History Module
+//!
+//! Contains functions for enumerating the deck's shards by replaying its `ShardSet` logs.
+//!
+//! The shard mapping cannot be enumerated from storage. A shard set to the zero address is
+//! removed, and the fallback reverts for its selector.
+use crate::{
+    constants::TOPIC_SHARD_SET,
+    error::Error,
+    events::{decode_log, DeckEvent},
+    storage::reader::read_shard_at,
+};
+use ethers::prelude::*;
+use std::collections::BTreeMap;
+
+/// ## Shard Change
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShardChange {
+    block: U64,
+    shard: Address,
+}
+
+impl ShardChange {
+    /// ### Create New Shard Change
+    ///
+    /// #### Parameters
+    ///
+    /// - `block`: The block the shard was set in.
+    /// - `shard`: The shard, the zero address if removed.
+    pub fn new(block: &U64, shard: &Address) -> Self {
+        Self {
+            block: *block,
+            shard: *shard,
+        }
+    }
+
+    /// ### Get the block the shard was set in
+    pub fn block(&self) -> &U64 {
+        &self.block
+    }
+
+    /// ### Get the shard
+    pub fn shard(&self) -> &Address {
+        &self.shard
+    }
+}
+
+/// ## Shard Entry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShardEntry {
+    selector: [u8; 4],
+    history: Vec<ShardChange>,
+    has_code: bool,
+}
+
+impl ShardEntry {
+    /// ### Create New Shard Entry
+    ///
+    /// #### Parameters
+    ///
+    /// - `selector`: The selector.
+    /// - `history`: The selector's shard changes, oldest first. Must not be empty.
+    /// - `has_code`: Whether the current shard has code.
+    pub fn new(selector: &[u8; 4], history: &[ShardChange], has_code: bool) -> Self {
+        Self {
+            selector: *selector,
+            history: history.to_vec(),
+            has_code,
+        }
+    }
+
+    /// ### Get the selector
+    pub fn selector(&self) -> &[u8; 4] {
+        &self.selector
+    }
+
+    /// ### Get the current shard, `None` if removed
+    pub fn shard(&self) -> Option<&Address> {
+        self.history
+            .last()
+            .map(ShardChange::shard)
+            .filter(|shard| !shard.is_zero())
+    }
+
+    /// ### Get the shard changes, oldest first
+    pub fn history(&self) -> &[ShardChange] {
+        &self.history
+    }
+
+    /// ### Get whether the current shard has code
+    pub fn has_code(&self) -> bool {
+        self.has_code
+    }
+
+    /// ### Get whether the current shard is set but has no code
+    ///
+    /// Delegating to an account without code succeeds without executing anything.
+    pub fn is_codeless(&self) -> bool {
+        self.shard().is_some() && !self.has_code
+    }
+}
+
+/// ## Replay ShardSet Logs
+///
+/// ### Parameters
+///
+/// - `logs`: The deck's `ShardSet` logs, in any order.
+///
+/// ### Returns
+///
+/// Either each selector's shard changes, oldest first, or an [Error](crate::error::Error) if a
+/// log is not a `ShardSet` log.
+pub fn replay_shard_logs(logs: &[Log]) -> Result<BTreeMap<[u8; 4], Vec<ShardChange>>, Error> {
+    let mut logs = logs.iter().collect::<Vec<&Log>>();
+    logs.sort_by_key(|log| (log.block_number, log.log_index));
+
+    let mut shards: BTreeMap<[u8; 4], Vec<ShardChange>> = BTreeMap::new();
+
+    for log in logs {
+        let event = match decode_log(log)? {
+            DeckEvent::ShardSet(event) => event,
+            _ => return Err(Error::MalformedLog),
+        };
+        let block = log.block_number.ok_or(Error::MalformedLog)?;
+
+        shards
+            .entry(*event.selector())
+            .or_default()
+            .push(ShardChange::new(&block, event.shard()));
+    }
+
+    Ok(shards)
+}
+
+/// ## Read Shards
+///
+/// Each selector's current shard is checked against the deck's shard storage at `block`.
+///
+/// ### Parameters
+///
+/// - `provider`: The provider to read the chain with.
+/// - `deck`: The deck's address.
+/// - `deployed_at`: The deck's deployment block.
+/// - `block`: The block to enumerate the shards at.
+///
+/// ### Returns
+///
+/// Either every selector ever set, including removed ones, ordered by selector, or an
+/// [Error](crate::error::Error).
+pub async fn read_shards<M>(
+    provider: M,
+    deck: &Address,
+    deployed_at: &U64,
+    block: &U64,
+) -> Result<Vec<ShardEntry>, Error>
+where
+    M: Middleware + Clone + 'static,
+{
+    let filter = Filter::new()
+        .address(*deck)
+        .topic0(H256(TOPIC_SHARD_SET))
+        .from_block(*deployed_at)
+        .to_block(*block);
+    let logs = provider
+        .get_logs(&filter)
+        .await
+        .map_err(|e| Error::Middleware(e.to_string()))?;
+
+    let mut entries = Vec::new();
+
+    for (selector, history) in replay_shard_logs(&logs)? {
+        let replayed = history
+            .last()
+            .map(|change| *change.shard())
+            .filter(|shard| !shard.is_zero());
+        let stored =
+            read_shard_at(provider.clone(), deck, &selector, Some((*block).into())).await?;
+
+        if replayed != stored {
+            return Err(Error::Inconsistent(format!(
+                "shard for 0x{} is {:?} at block {}, expected {:?}",
+                ethers::utils::hex::encode(selector),
+                stored,
+                block,
+                replayed
+            )));
+        }
+
+        let has_code = match stored {
+            Some(shard) => !provider
+                .get_code(shard, Some((*block).into()))
+                .await
+                .map_err(|e| Error::Middleware(e.to_string()))?
+                .is_empty(),
+            None => false,
+        };
+
+        entries.push(ShardEntry::new(&selector, &history, has_code));
+    }
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shard_log(selector: u8, shard: u8, block: u64, index: u64) -> Log {
+        let mut selector_topic = [0u8; 32];
+        selector_topic[..4].copy_from_slice(&[selector; 4]);
+        Log {
+            topics: vec![
+                H256(TOPIC_SHARD_SET),
+                H256(selector_topic),
+                H256::from(Address::repeat_byte(shard)),
+            ],
+            block_number: Some(U64::from(block)),
+            log_index: Some(U256::from(index)),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_replay_shard_history() {
+        let logs = [shard_log(1, 0xa, 2, 0), shard_log(2, 0xb, 2, 1), shard_log(1, 0xc, 3, 0)];
+        let shards = replay_shard_logs(&logs).unwrap();
+
+        assert_eq!(shards.len(), 2);
+        assert_eq!(
+            shards[&[1; 4]],
+            vec![
+                ShardChange::new(&U64::from(2), &Address::repeat_byte(0xa)),
+                ShardChange::new(&U64::from(3), &Address::repeat_byte(0xc)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_replay_shard_orders_logs() {
+        let logs = [shard_log(1, 0, 4, 0), shard_log(1, 0xa, 2, 0)];
+        let shards = replay_shard_logs(&logs).unwrap();
+        let entry = ShardEntry::new(&[1; 4], &shards[&[1; 4]], false);
+
+        assert_eq!(entry.shard(), None);
+        assert!(!entry.is_codeless());
+    }
+
+    #[test]
+    fn test_shard_entry_codeless() {
+        let history = [ShardChange::new(&U64::one(), &Address::repeat_byte(0xa))];
+
+        assert!(ShardEntry::new(&[1; 4], &history, false).is_codeless());
+        assert!(!ShardEntry::new(&[1; 4], &history, true).is_codeless());
+    }
+
+    #[tokio::test]
+    async fn test_read_shards() {
+        let (provider, mock) = Provider::mocked();
+        // responses are popped in reverse order
+        mock.push::<Bytes, _>(Bytes::new()).unwrap();
+        mock.push(H256::from(Address::repeat_byte(0xb))).unwrap();
+        mock.push::<Bytes, _>(Bytes::from(vec![0x00])).unwrap();
+        mock.push(H256::from(Address::repeat_byte(0xa))).unwrap();
+        mock.push::<Vec<Log>, _>(vec![shard_log(1, 0xa, 2, 0), shard_log(2, 0xb, 3, 0)])
+            .unwrap();
+
+        let shards = read_shards(provider, &Address::zero(), &U64::one(), &U64::from(10))
+            .await
+            .unwrap();
+
+        assert_eq!(shards.len(), 2);
+        assert_eq!(shards[0].shard(), Some(&Address::repeat_byte(0xa)));
+        assert!(shards[0].has_code());
+        assert!(shards[1].is_codeless());
+    }
+
+    #[tokio::test]
+    async fn test_read_shards_inconsistent() {
+        let (provider, mock) = Provider::mocked();
+        mock.push(H256::zero()).unwrap();
+        mock.push::<Vec<Log>, _>(vec![shard_log(1, 0xa, 2, 0)]).unwrap();
+
+        let result = read_shards(provider, &Address::zero(), &U64::one(), &U64::from(10)).await;
+
+        assert!(matches!(result, Err(Error::Inconsistent(_))));
+    }
+}
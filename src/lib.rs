@@ -1,6 +1,20 @@
+pub mod bytecode;
 pub mod coder;
 pub mod constants;
 pub mod error;
+pub mod events;
+pub mod gas;
+pub mod governance;
+pub mod history;
+pub mod deck;
 pub mod deploy;
+pub mod digest;
+pub mod preflight;
+pub mod revert;
+pub mod safety;
+pub mod sign;
+pub mod simulate;
 pub mod storage;
+pub mod trace;
 pub mod util;
+pub mod verify;
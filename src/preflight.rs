@@ -0,0 +1,420 @@
+//! # Ether Deck Pre-flight Module
+//!
+//! Contains functions for dry-running a syscall's inner call before its signatures are gathered.
+//!
+//! The call is made against state overrides that set the deck's id to the syscall's id, set the
+//! threshold to zero, and authorize a throwaway signer. The deck still reads one signature with a
+//! zero threshold, so the syscall is signed by the throwaway signer.
+//!
+//! > Note: The result comes from `eth_call` with state overrides, which most providers serve.
+//! > `eth_call` returns neither logs nor gas, so these are read with `debug_traceCall` and geth's
+//! > `callTracer`, which take the same overrides. Providers without the `debug` namespace yield a
+//! > report without logs or gas.
+use crate::{
+    coder::{
+        encoder::encode,
+        structures::{Encoding, Syscall},
+    },
+    error::Error,
+    revert::decode_middleware_error,
+    sign::sign_syscall,
+    storage::slot::{auth_slot, id_slot, threshold_slot},
+    util::u256_to_be,
+};
+use ethers::{
+    prelude::*,
+    providers::call_raw::RawCall,
+    types::{spoof, transaction::eip2718::TypedTransaction},
+    utils::keccak256,
+};
+
+/// ## Pre-flight Report
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Preflight {
+    success: bool,
+    output: Vec<u8>,
+    logs: Option<Vec<Log>>,
+    gas_used: Option<U256>,
+}
+
+impl Preflight {
+    /// ### Get whether the inner call succeeds
+    pub fn is_success(&self) -> bool {
+        self.success
+    }
+
+    /// ### Get the inner call's return or revert data
+    pub fn output(&self) -> &[u8] {
+        &self.output
+    }
+
+    /// ### Get the inner call's logs, `None` if the call could not be traced
+    ///
+    /// Logs are ordered by call frame, depth first, rather than by emission.
+    pub fn logs(&self) -> Option<&[Log]> {
+        self.logs.as_deref()
+    }
+
+    /// ### Get the gas used by the inner call, `None` if the call could not be traced
+    pub fn gas_used(&self) -> Option<&U256> {
+        self.gas_used.as_ref()
+    }
+}
+
+/// ## Pre-flight Signer
+///
+/// ### Returns
+///
+/// The throwaway signer authorized by the pre-flight overrides.
+pub fn preflight_signer() -> LocalWallet {
+    LocalWallet::from_bytes(&keccak256("decktools.preflight")).expect("valid private key")
+}
+
+/// ## Pre-flight Overrides
+///
+/// ### Parameters
+///
+/// - `deck`: The deck's address.
+/// - `id`: The syscall's id.
+/// - `signer`: The account to authorize.
+///
+/// ### Returns
+///
+/// The state overrides.
+pub fn preflight_overrides(deck: &Address, id: &U256, signer: &Address) -> spoof::State {
+    let mut state = spoof::state();
+    state
+        .account(*deck)
+        .store(H256(u256_to_be(&id_slot())), H256(u256_to_be(id)))
+        .store(H256(u256_to_be(&threshold_slot())), H256::zero())
+        .store(H256(auth_slot(signer)), H256::from_low_u64_be(1));
+    state
+}
+
+/// ## Pre-flight Syscall
+///
+/// Existing signatures on the syscall are ignored. The deck returns the inner call's return data
+/// and bubbles its revert data, so the result is read with `eth_call`. The call is then traced
+/// for logs and gas, which are left empty if the provider does not serve `debug_traceCall`.
+///
+/// ### Parameters
+///
+/// - `provider`: The provider to call with.
+/// - `deck`: The deck's address.
+/// - `syscall`: The syscall to dry-run.
+/// - `block`: The block to call at, `latest` if `None`.
+///
+/// ### Returns
+///
+/// Either the inner call's [Preflight] report or an [Error](crate::error::Error) if the deck
+/// reverts before making the inner call, such as with an expired deadline.
+pub async fn preflight_syscall<M>(
+    provider: M,
+    deck: &Address,
+    syscall: &Syscall,
+    block: Option<BlockId>,
+) -> Result<Preflight, Error>
+where
+    M: Middleware + 'static,
+{
+    let signer = preflight_signer();
+    let overrides = preflight_overrides(deck, syscall.id(), &signer.address());
+    let chain_id = provider
+        .get_chainid()
+        .await
+        .map_err(|e| Error::Middleware(e.to_string()))?;
+    let unsigned = Syscall::new(
+        syscall.id(),
+        syscall.target(),
+        syscall.value(),
+        syscall.deadline(),
+        syscall.payload(),
+        &[],
+    );
+    let signed = sign_syscall(&unsigned, &chain_id, &[signer]).await?;
+
+    let tx: TypedTransaction = Eip1559TransactionRequest::new()
+        .to(*deck)
+        .data(encode(&Encoding::Syscall(signed))?)
+        .into();
+
+    let mut call = provider.provider().call_raw(&tx).state(&overrides);
+    if let Some(block) = block {
+        call = call.block(block);
+    }
+    let (success, output) = match call.await {
+        Ok(output) => (true, output.to_vec()),
+        Err(e) => match decode_middleware_error(&e) {
+            Error::Revert(data) => (false, data),
+            error => return Err(error),
+        },
+    };
+
+    let (logs, gas_used) = match trace_inner(&provider, tx, block, overrides, syscall).await {
+        Some(inner) => {
+            let mut logs = Vec::new();
+            collect_logs(&inner, &mut logs);
+            (Some(logs), Some(inner.gas_used))
+        }
+        None => (None, None),
+    };
+
+    Ok(Preflight {
+        success,
+        output,
+        logs,
+        gas_used,
+    })
+}
+
+/// Traces a pre-flight call, returning the inner call's frame.
+///
+/// The deck's `ecrecover` calls are `STATICCALL` frames to the precompile, made before the inner
+/// call, so the inner call is the last `CALL` frame to the syscall's target.
+async fn trace_inner<M>(
+    provider: &M,
+    tx: TypedTransaction,
+    block: Option<BlockId>,
+    overrides: spoof::State,
+    syscall: &Syscall,
+) -> Option<CallFrame>
+where
+    M: Middleware + 'static,
+{
+    let options = GethDebugTracingCallOptions {
+        tracing_options: GethDebugTracingOptions {
+            tracer: Some(GethDebugTracerType::BuiltInTracer(
+                GethDebugBuiltInTracerType::CallTracer,
+            )),
+            tracer_config: Some(GethDebugTracerConfig::BuiltInTracer(
+                GethDebugBuiltInTracerConfig::CallTracer(CallConfig {
+                    only_top_call: None,
+                    with_log: Some(true),
+                }),
+            )),
+            ..Default::default()
+        },
+        state_overrides: Some(overrides),
+        block_overrides: None,
+    };
+
+    let frame = match provider.debug_trace_call(tx, block, options).await.ok()? {
+        GethTrace::Known(GethTraceFrame::CallTracer(frame)) => frame,
+        _ => return None,
+    };
+
+    frame.calls?.into_iter().rev().find(|call| {
+        call.typ == "CALL" && call.to == Some(NameOrAddress::Address(*syscall.target()))
+    })
+}
+
+/// Collects a frame's logs, then its sub-calls' logs, depth first.
+fn collect_logs(frame: &CallFrame, logs: &mut Vec<Log>) {
+    let emitter = match &frame.to {
+        Some(NameOrAddress::Address(address)) => Some(*address),
+        _ => None,
+    };
+
+    for log in frame.logs.iter().flatten() {
+        logs.push(Log {
+            address: log.address.or(emitter).unwrap_or_default(),
+            topics: log.topics.clone().unwrap_or_default(),
+            data: log.data.clone().unwrap_or_default(),
+            ..Default::default()
+        });
+    }
+    for call in frame.calls.iter().flatten() {
+        collect_logs(call, logs);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::ERROR_DEADLINE_EXPIRED;
+    use ethers::{
+        providers::{JsonRpcError, MockResponse},
+        utils::hex,
+    };
+    use serde_json::json;
+
+    fn syscall() -> Syscall {
+        Syscall::new(
+            &U256::from(4),
+            &Address::repeat_byte(0x7a),
+            &U256::zero(),
+            &U64::from(100),
+            &[0xab],
+            &[],
+        )
+    }
+
+    #[test]
+    fn test_preflight_overrides() {
+        let deck = Address::repeat_byte(0xde);
+        let signer = preflight_signer().address();
+        let state = serde_json::to_value(preflight_overrides(&deck, &U256::from(4), &signer))
+            .unwrap();
+
+        let diff = &state[format!("{:?}", deck)]["stateDiff"];
+        assert_eq!(diff[format!("{:?}", H256::zero())], json!(H256::from_low_u64_be(4)));
+        assert_eq!(diff[format!("{:?}", H256::from_low_u64_be(1))], json!(H256::zero()));
+        assert_eq!(
+            diff[format!("{:?}", H256(auth_slot(&signer)))],
+            json!(H256::from_low_u64_be(1))
+        );
+    }
+
+    /// The deck's `ecrecover` frame, traced before the inner call.
+    fn ecrecover_frame() -> serde_json::Value {
+        json!({
+            "type": "STATICCALL",
+            "from": Address::repeat_byte(0xde),
+            "to": Address::from_low_u64_be(1),
+            "gas": "0x9000",
+            "gasUsed": "0xbb8",
+            "input": "0x",
+            "output": Bytes::from(H256::from(preflight_signer().address()).as_bytes().to_vec())
+        })
+    }
+
+    fn revert_response(data: &[u8]) -> MockResponse {
+        MockResponse::Error(JsonRpcError {
+            code: 3,
+            message: "execution reverted".to_string(),
+            data: Some(serde_json::Value::String(format!("0x{}", hex::encode(data)))),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_preflight_syscall() {
+        let target = Address::repeat_byte(0x7a);
+        let (provider, mock) = Provider::mocked();
+        // responses are popped in reverse order
+        mock.push(json!({
+            "type": "CALL",
+            "from": Address::repeat_byte(1),
+            "to": Address::repeat_byte(0xde),
+            "gas": "0x10000",
+            "gasUsed": "0x9000",
+            "input": "0x",
+            "output": "0x2a",
+            "calls": [ecrecover_frame(), {
+                "type": "CALL",
+                "from": Address::repeat_byte(0xde),
+                "to": target,
+                "gas": "0x8000",
+                "gasUsed": "0x5208",
+                "input": "0xab",
+                "output": "0x2a",
+                "logs": [{ "topics": [H256::repeat_byte(1)], "data": "0x01" }],
+                "calls": [{
+                    "type": "CALL",
+                    "from": target,
+                    "to": Address::repeat_byte(0x0c),
+                    "gas": "0x4000",
+                    "gasUsed": "0x100",
+                    "input": "0x",
+                    "logs": [{ "address": Address::repeat_byte(0x0c), "topics": [], "data": "0x" }]
+                }]
+            }]
+        }))
+        .unwrap();
+        mock.push::<Bytes, _>(Bytes::from(vec![0x2a])).unwrap();
+        mock.push(U256::from(31337)).unwrap();
+
+        let report = preflight_syscall(provider, &Address::repeat_byte(0xde), &syscall(), None)
+            .await
+            .unwrap();
+
+        assert!(report.is_success());
+        assert_eq!(report.output(), &[0x2a]);
+        assert_eq!(report.gas_used(), Some(&U256::from(0x5208)));
+        let logs = report.logs().unwrap();
+        assert_eq!(logs.len(), 2);
+        assert_eq!(logs[0].address, target);
+        assert_eq!(logs[0].topics, vec![H256::repeat_byte(1)]);
+        assert_eq!(logs[1].address, Address::repeat_byte(0x0c));
+    }
+
+    #[tokio::test]
+    async fn test_preflight_inner_revert() {
+        let (provider, mock) = Provider::mocked();
+        mock.push(json!({
+            "type": "CALL",
+            "from": Address::repeat_byte(1),
+            "to": Address::repeat_byte(0xde),
+            "gas": "0x10000",
+            "gasUsed": "0x9000",
+            "input": "0x",
+            "output": "0x01",
+            "error": "execution reverted",
+            "calls": [ecrecover_frame(), {
+                "type": "CALL",
+                "from": Address::repeat_byte(0xde),
+                "to": Address::repeat_byte(0x7a),
+                "gas": "0x8000",
+                "gasUsed": "0x100",
+                "input": "0xab",
+                "output": "0x01",
+                "error": "execution reverted"
+            }]
+        }))
+        .unwrap();
+        mock.push_response(revert_response(&[0x01]));
+        mock.push(U256::from(31337)).unwrap();
+
+        let report = preflight_syscall(provider, &Address::repeat_byte(0xde), &syscall(), None)
+            .await
+            .unwrap();
+
+        assert!(!report.is_success());
+        assert_eq!(report.output(), &[0x01]);
+        assert_eq!(report.gas_used(), Some(&U256::from(0x100)));
+        assert_eq!(report.logs(), Some(&[][..]));
+    }
+
+    #[tokio::test]
+    async fn test_preflight_deck_revert() {
+        let (provider, mock) = Provider::mocked();
+        mock.push_response(revert_response(&ERROR_DEADLINE_EXPIRED));
+        mock.push(U256::from(31337)).unwrap();
+
+        let result =
+            preflight_syscall(provider, &Address::repeat_byte(0xde), &syscall(), None).await;
+
+        assert_eq!(result, Err(Error::DeadlineExpired));
+
+        let deck = Address::repeat_byte(0xde);
+        let signer = preflight_signer();
+        let overrides = preflight_overrides(&deck, &U256::from(4), &signer.address());
+        let signed = sign_syscall(&syscall(), &U256::from(31337), &[signer]).await.unwrap();
+        let tx: TypedTransaction = Eip1559TransactionRequest::new()
+            .to(deck)
+            .data(encode(&Encoding::Syscall(signed)).unwrap())
+            .into();
+        mock.assert_request("eth_chainId", ()).unwrap();
+        mock.assert_request("eth_call", (tx, BlockNumber::Latest, overrides)).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_preflight_untraced() {
+        let (provider, mock) = Provider::mocked();
+        mock.push_response(MockResponse::Error(JsonRpcError {
+            code: -32601,
+            message: "the method debug_traceCall does not exist/is not available".to_string(),
+            data: None,
+        }));
+        mock.push::<Bytes, _>(Bytes::from(vec![0x2a])).unwrap();
+        mock.push(U256::from(31337)).unwrap();
+
+        let report = preflight_syscall(provider, &Address::repeat_byte(0xde), &syscall(), None)
+            .await
+            .unwrap();
+
+        assert!(report.is_success());
+        assert_eq!(report.output(), &[0x2a]);
+        assert_eq!(report.logs(), None);
+        assert_eq!(report.gas_used(), None);
+    }
+}
@@ -0,0 +1,193 @@
+//! # Ether Deck Revert Module
+//!
+//! Contains functions for decoding the deck's revert data into typed
+//! [Error](crate::error::Error) variants.
+//!
+//! > Note: A syscall bubbles its inner call's revert data unchanged. Inner revert data that is
+//! > exactly one of the deck's error selectors is indistinguishable from the deck's own revert.
+use crate::{
+    constants::{ERROR_DEADLINE_EXPIRED, ERROR_SHARD_NOT_FOUND, ERROR_UNAUTHORIZED},
+    error::Error,
+};
+use ethers::{
+    prelude::*,
+    providers::MiddlewareError,
+    types::transaction::eip2718::TypedTransaction,
+};
+
+/// ## Decode Revert Data
+///
+/// ### Parameters
+///
+/// - `data`: The revert data.
+///
+/// ### Returns
+///
+/// The typed deck [Error](crate::error::Error), or [Revert](crate::error::Error::Revert) with
+/// the data passed through.
+pub fn decode_revert(data: &[u8]) -> Error {
+    match data.try_into() {
+        Ok(ERROR_SHARD_NOT_FOUND) => Error::ShardNotFound,
+        Ok(ERROR_DEADLINE_EXPIRED) => Error::DeadlineExpired,
+        Ok(ERROR_UNAUTHORIZED) => Error::Unauthorized,
+        _ => Error::Revert(data.to_vec()),
+    }
+}
+
+/// ## Decode Middleware Error
+///
+/// Applies to errors from `eth_call`, `eth_estimateGas`, and transaction submission.
+///
+/// ### Parameters
+///
+/// - `error`: The middleware error.
+///
+/// ### Returns
+///
+/// The [decoded revert](decode_revert) if the error carries revert data, otherwise
+/// [Middleware](crate::error::Error::Middleware).
+pub fn decode_middleware_error<E: MiddlewareError>(error: &E) -> Error {
+    match error.as_error_response().and_then(|e| e.as_revert_data()) {
+        Some(data) => decode_revert(&data),
+        None => Error::Middleware(error.to_string()),
+    }
+}
+
+/// ## Decode Receipt Revert
+///
+/// Receipts carry no revert data, so a failed transaction is replayed with `eth_call` against the
+/// state of the block before its inclusion. The replay does not include transactions earlier in
+/// the same block.
+///
+/// ### Parameters
+///
+/// - `provider`: The provider to replay the transaction with.
+/// - `receipt`: The transaction receipt.
+///
+/// ### Returns
+///
+/// Either the decoded revert, `None` if the transaction succeeded or the replay does not revert,
+/// or an [Error](crate::error::Error) if the replay cannot be made.
+pub async fn decode_receipt_revert<M>(
+    provider: M,
+    receipt: &TransactionReceipt,
+) -> Result<Option<Error>, Error>
+where
+    M: Middleware + 'static,
+{
+    if receipt.status != Some(U64::zero()) {
+        return Ok(None);
+    }
+
+    let tx = provider
+        .get_transaction(receipt.transaction_hash)
+        .await
+        .map_err(|e| Error::Middleware(e.to_string()))?
+        .ok_or(Error::Middleware("No transaction".to_string()))?;
+    let block = receipt
+        .block_number
+        .ok_or(Error::Middleware("No block number".to_string()))?
+        .saturating_sub(U64::one());
+
+    let tx: TypedTransaction = (&tx).into();
+
+    match provider.call(&tx, Some(block.into())).await {
+        Ok(_) => Ok(None),
+        Err(e) => Ok(Some(decode_middleware_error(&e))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::providers::{JsonRpcError, MockResponse};
+
+    fn revert_response(data: &str) -> MockResponse {
+        MockResponse::Error(JsonRpcError {
+            code: 3,
+            message: "execution reverted".to_string(),
+            data: Some(serde_json::Value::String(data.to_string())),
+        })
+    }
+
+    #[test]
+    fn test_decode_revert_deck_errors() {
+        assert_eq!(decode_revert(&[0xef, 0x16, 0x0e, 0x8e]), Error::ShardNotFound);
+        assert_eq!(decode_revert(&[0xc6, 0xf2, 0x21, 0xaa]), Error::DeadlineExpired);
+        assert_eq!(decode_revert(&[0xab, 0x8a, 0x03, 0x60]), Error::Unauthorized);
+    }
+
+    #[test]
+    fn test_decode_revert_passthrough() {
+        // `Error(string)` from an inner call
+        let data = [0x08, 0xc3, 0x79, 0xa0, 0x00];
+
+        assert_eq!(decode_revert(&data), Error::Revert(data.to_vec()));
+        assert_eq!(decode_revert(&[]), Error::Revert(vec![]));
+    }
+
+    #[test]
+    fn test_decode_revert_selector_prefix() {
+        let data = [0xab, 0x8a, 0x03, 0x60, 0x00];
+
+        assert_eq!(decode_revert(&data), Error::Revert(data.to_vec()));
+    }
+
+    #[tokio::test]
+    async fn test_decode_middleware_error() {
+        let (provider, mock) = Provider::mocked();
+        mock.push_response(revert_response("0xc6f221aa"));
+
+        let error = provider
+            .call(&TypedTransaction::default(), None)
+            .await
+            .unwrap_err();
+
+        assert_eq!(decode_middleware_error(&error), Error::DeadlineExpired);
+    }
+
+    #[tokio::test]
+    async fn test_decode_middleware_error_not_revert() {
+        let (provider, mock) = Provider::mocked();
+        mock.push_response(MockResponse::Error(JsonRpcError {
+            code: -32000,
+            message: "header not found".to_string(),
+            data: None,
+        }));
+
+        let error = provider
+            .call(&TypedTransaction::default(), None)
+            .await
+            .unwrap_err();
+
+        assert!(matches!(decode_middleware_error(&error), Error::Middleware(_)));
+    }
+
+    #[tokio::test]
+    async fn test_decode_receipt_revert() {
+        let (provider, mock) = Provider::mocked();
+        let receipt = TransactionReceipt {
+            status: Some(U64::zero()),
+            block_number: Some(U64::from(10)),
+            ..Default::default()
+        };
+        // responses are popped in reverse order
+        mock.push_response(revert_response("0xab8a0360"));
+        mock.push(Transaction::default()).unwrap();
+
+        let error = decode_receipt_revert(provider, &receipt).await.unwrap();
+
+        assert_eq!(error, Some(Error::Unauthorized));
+    }
+
+    #[tokio::test]
+    async fn test_decode_receipt_revert_success() {
+        let (provider, _) = Provider::mocked();
+        let receipt = TransactionReceipt {
+            status: Some(U64::one()),
+            ..Default::default()
+        };
+
+        assert_eq!(decode_receipt_revert(provider, &receipt).await.unwrap(), None);
+    }
+}
@@ -0,0 +1,278 @@
+//! # Ether Deck Lockout Safety Module
+//!
+//! Contains an analyzer that projects governance changes onto the owner set and threshold and
+//! rejects changes that could lock the deck.
+//!
+//! > Note: The deck reads at least one signature even with a zero threshold, but a zero threshold
+//! > is never intended and is rejected.
+use crate::{
+    coder::{
+        decoder::decode,
+        structures::{Encoding, Syscall},
+    },
+    error::{Error, Violation},
+    history::owners::Owner,
+};
+use ethers::prelude::Address;
+use std::collections::BTreeSet;
+
+/// ## Projection
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Projection {
+    owners: BTreeSet<Address>,
+    threshold: u8,
+}
+
+impl Projection {
+    /// ### Create New Projection
+    ///
+    /// #### Parameters
+    ///
+    /// - `owners`: The current owners, as enumerated by
+    ///   [read_owners](crate::history::owners::read_owners).
+    /// - `threshold`: The current threshold.
+    pub fn new(owners: &[Owner], threshold: u8) -> Self {
+        Self {
+            owners: owners.iter().map(|owner| *owner.account()).collect(),
+            threshold,
+        }
+    }
+
+    /// ### Get the owners, ordered by address
+    pub fn owners(&self) -> Vec<Address> {
+        self.owners.iter().copied().collect()
+    }
+
+    /// ### Get the threshold
+    pub fn threshold(&self) -> u8 {
+        self.threshold
+    }
+
+    /// ### Apply a Change
+    ///
+    /// `SetAuth` and `SetThreshold` change the projection and `SetShard` leaves it unchanged.
+    /// A shard call or a nested syscall runs code that could rewrite the owners or threshold, so
+    /// it is rejected as [Unverifiable](crate::error::Violation::Unverifiable).
+    ///
+    /// #### Parameters
+    ///
+    /// - `change`: The governance call.
+    ///
+    /// #### Returns
+    ///
+    /// Either the projection after the change or a [Lockout](crate::error::Error::Lockout) error.
+    pub fn apply(&self, change: &Encoding) -> Result<Projection, Error> {
+        let mut next = self.clone();
+
+        match change {
+            Encoding::SetAuth(data) if data.authorized() => {
+                next.owners.insert(*data.account());
+            }
+            Encoding::SetAuth(data) => {
+                next.owners.remove(data.account());
+            }
+            Encoding::SetThreshold(data) => next.threshold = data.threshold(),
+            Encoding::SetShard(_) => return Ok(next),
+            Encoding::ShardCall { .. } | Encoding::Syscall(_) => {
+                return Err(Error::Lockout(Violation::Unverifiable))
+            }
+        }
+
+        next.check()?;
+        Ok(next)
+    }
+
+    /// ### Apply a Proposal
+    ///
+    /// Only syscalls targeting the deck can change its owners or threshold.
+    ///
+    /// #### Parameters
+    ///
+    /// - `deck`: The deck's address.
+    /// - `proposal`: The syscall.
+    ///
+    /// #### Returns
+    ///
+    /// Either the projection after the syscall or an [Error](crate::error::Error).
+    pub fn apply_proposal(&self, deck: &Address, proposal: &Syscall) -> Result<Projection, Error> {
+        if proposal.target() != deck {
+            return Ok(self.clone());
+        }
+
+        self.apply(&decode(proposal.payload())?)
+    }
+
+    /// ### Check the projection is safe
+    ///
+    /// The zero address is not counted as an owner, since no key signs for it.
+    pub fn check(&self) -> Result<(), Error> {
+        let owners = self.owners.iter().filter(|owner| !owner.is_zero()).count();
+
+        if owners == 0 {
+            return Err(Error::Lockout(Violation::NoOwners));
+        }
+        if self.threshold == 0 {
+            return Err(Error::Lockout(Violation::ZeroThreshold));
+        }
+        if self.threshold as usize > owners {
+            return Err(Error::Lockout(Violation::ThresholdExceedsOwners {
+                threshold: self.threshold,
+                owners,
+            }));
+        }
+        Ok(())
+    }
+}
+
+/// ## Checked Threshold
+///
+/// ### Parameters
+///
+/// - `threshold`: The intended threshold.
+///
+/// ### Returns
+///
+/// Either the threshold as the deck stores it or a [Lockout](crate::error::Error::Lockout)
+/// error if it would be truncated.
+pub fn checked_threshold(threshold: usize) -> Result<u8, Error> {
+    u8::try_from(threshold).map_err(|_| Error::Lockout(Violation::ThresholdOverflow(threshold)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        coder::{
+            encoder::encode,
+            structures::{SetAuth, SetShard, SetThreshold},
+        },
+        governance::{add_owner, remove_owner},
+    };
+    use ethers::prelude::{U256, U64};
+
+    fn projection(owners: &[u8], threshold: u8) -> Projection {
+        let owners = owners
+            .iter()
+            .map(|owner| Owner::new(&Address::repeat_byte(*owner), &U64::one()))
+            .collect::<Vec<Owner>>();
+        Projection::new(&owners, threshold)
+    }
+
+    fn set_auth(owner: u8, authorized: bool) -> Encoding {
+        Encoding::SetAuth(SetAuth::new(&Address::repeat_byte(owner), authorized))
+    }
+
+    #[test]
+    fn test_apply_safe_changes() {
+        let next = projection(&[1], 1)
+            .apply(&set_auth(2, true))
+            .and_then(|next| next.apply(&Encoding::SetThreshold(SetThreshold::new(2))))
+            .unwrap();
+
+        assert_eq!(next.owners(), vec![Address::repeat_byte(1), Address::repeat_byte(2)]);
+        assert_eq!(next.threshold(), 2);
+    }
+
+    #[test]
+    fn test_threshold_exceeds_owners() {
+        assert_eq!(
+            projection(&[1, 2], 1).apply(&Encoding::SetThreshold(SetThreshold::new(3))),
+            Err(Error::Lockout(Violation::ThresholdExceedsOwners {
+                threshold: 3,
+                owners: 2
+            }))
+        );
+        // removing an owner below the threshold
+        assert!(projection(&[1, 2], 2).apply(&set_auth(1, false)).is_err());
+    }
+
+    #[test]
+    fn test_zero_threshold() {
+        assert_eq!(
+            projection(&[1], 1).apply(&Encoding::SetThreshold(SetThreshold::new(0))),
+            Err(Error::Lockout(Violation::ZeroThreshold))
+        );
+    }
+
+    #[test]
+    fn test_remove_last_owner() {
+        assert_eq!(
+            projection(&[1], 1).apply(&set_auth(1, false)),
+            Err(Error::Lockout(Violation::NoOwners))
+        );
+    }
+
+    #[test]
+    fn test_threshold_overflow() {
+        assert_eq!(checked_threshold(255), Ok(255));
+        assert_eq!(
+            checked_threshold(256),
+            Err(Error::Lockout(Violation::ThresholdOverflow(256)))
+        );
+    }
+
+    #[test]
+    fn test_zero_address_owner() {
+        let next = projection(&[1], 1)
+            .apply(&Encoding::SetAuth(SetAuth::new(&Address::zero(), true)))
+            .unwrap();
+
+        assert_eq!(next.owners().len(), 2);
+        assert_eq!(
+            next.apply(&Encoding::SetThreshold(SetThreshold::new(2))),
+            Err(Error::Lockout(Violation::ThresholdExceedsOwners {
+                threshold: 2,
+                owners: 1
+            }))
+        );
+        assert_eq!(
+            next.apply(&set_auth(1, false)),
+            Err(Error::Lockout(Violation::NoOwners))
+        );
+    }
+
+    #[test]
+    fn test_unverifiable_change() {
+        let deck = Address::repeat_byte(0xde);
+        let current = projection(&[1], 1);
+        let shard_call = Encoding::ShardCall {
+            selector: [0xaa; 4],
+            data: vec![],
+        };
+
+        assert_eq!(current.apply(&shard_call), Err(Error::Lockout(Violation::Unverifiable)));
+
+        let nested = add_owner(&deck, &U256::one(), &Address::repeat_byte(2), &U64::one());
+        let payload = encode(&Encoding::Syscall(nested)).unwrap();
+        let proposal =
+            Syscall::new(&U256::zero(), &deck, &U256::zero(), &U64::one(), &payload, &[]);
+
+        assert_eq!(
+            current.apply_proposal(&deck, &proposal),
+            Err(Error::Lockout(Violation::Unverifiable))
+        );
+    }
+
+    #[test]
+    fn test_unrelated_change() {
+        let current = projection(&[1], 1);
+        let change = Encoding::SetShard(SetShard::new(&[1; 4], &Address::zero()));
+
+        assert_eq!(current.apply(&change), Ok(current));
+    }
+
+    #[test]
+    fn test_apply_proposal() {
+        let deck = Address::repeat_byte(0xde);
+        let current = projection(&[1, 2], 2);
+
+        let remove = remove_owner(&deck, &U256::zero(), &Address::repeat_byte(1), &U64::one());
+        assert!(current.apply_proposal(&deck, &remove).is_err());
+
+        let add = add_owner(&deck, &U256::zero(), &Address::repeat_byte(3), &U64::one());
+        assert_eq!(current.apply_proposal(&deck, &add).unwrap().owners().len(), 3);
+
+        // a syscall to another target does not change the deck
+        assert_eq!(current.apply_proposal(&Address::zero(), &remove), Ok(current));
+    }
+}
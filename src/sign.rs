@@ -0,0 +1,175 @@
+//! # Ether Deck Signing Module
+//!
+//! Contains functions for signing syscalls.
+//!
+//! The deck requires each recovered signer to be strictly greater than the previous one, so
+//! signatures are ordered by the signer's address.
+use crate::{
+    coder::structures::{DeckSignature, Syscall},
+    digest::{syscall_digest, syscall_hash},
+    error::Error,
+};
+use ethers::{
+    prelude::{Address, Signature, H256, U256},
+    signers::Signer,
+};
+use std::collections::BTreeMap;
+
+/// ## Sign Syscall
+///
+/// Any signatures already on the syscall are kept, such that signatures collected from other
+/// parties may be merged.
+///
+/// ### Parameters
+///
+/// - `syscall`: The syscall to sign.
+/// - `chain_id`: The chain id the syscall is executed on.
+/// - `signers`: The signers to sign with.
+///
+/// ### Returns
+///
+/// Either the signed syscall, with one signature per signer in ascending signer address order,
+/// or an [Error](crate::error::Error).
+pub async fn sign_syscall<S: Signer>(
+    syscall: &Syscall,
+    chain_id: &U256,
+    signers: &[S],
+) -> Result<Syscall, Error> {
+    let hash = syscall_hash(syscall, chain_id)?;
+
+    let mut signatures = Vec::with_capacity(syscall.signatures().len() + signers.len());
+    signatures.extend_from_slice(syscall.signatures());
+
+    for signer in signers {
+        let signature = signer
+            .sign_message(hash.as_bytes())
+            .await
+            .map_err(|e| Error::Signer(e.to_string()))?;
+        signatures.push(DeckSignature::try_from(signature)?);
+    }
+
+    order_signatures(syscall, chain_id, &signatures)
+}
+
+/// ## Order Signatures
+///
+/// ### Parameters
+///
+/// - `syscall`: The syscall the signatures are for. Its signatures are ignored.
+/// - `chain_id`: The chain id the syscall is executed on.
+/// - `signatures`: The signatures to order.
+///
+/// ### Returns
+///
+/// Either the syscall with the signatures deduplicated by signer and in ascending signer address
+/// order, or an [Error](crate::error::Error) if a signer cannot be recovered.
+pub fn order_signatures(
+    syscall: &Syscall,
+    chain_id: &U256,
+    signatures: &[DeckSignature],
+) -> Result<Syscall, Error> {
+    let digest = syscall_digest(syscall, chain_id)?;
+
+    let ordered = signatures
+        .iter()
+        .map(|signature| Ok((recover_signer(signature, &digest)?, *signature)))
+        .collect::<Result<BTreeMap<Address, DeckSignature>, Error>>()?;
+
+    Ok(Syscall::new(
+        syscall.id(),
+        syscall.target(),
+        syscall.value(),
+        syscall.deadline(),
+        syscall.payload(),
+        &ordered.into_values().collect::<Vec<DeckSignature>>(),
+    ))
+}
+
+/// ## Recover Signer
+///
+/// ### Parameters
+///
+/// - `signature`: The signature to recover.
+/// - `digest`: The [syscall digest](crate::digest::syscall_digest).
+///
+/// ### Returns
+///
+/// Either the signer's address or an [Error](crate::error::Error).
+pub fn recover_signer(signature: &DeckSignature, digest: &H256) -> Result<Address, Error> {
+    Signature::from(*signature)
+        .recover(*digest)
+        .map_err(|e| Error::Signer(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::prelude::{LocalWallet, U64};
+
+    const KEYS: [&str; 3] = [
+        "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+        "59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d",
+        "5de4111afa1a4b94908f83103eb1f1706367c2e68ca870fc3fb9a804cdf443a6",
+    ];
+
+    fn wallets() -> Vec<LocalWallet> {
+        KEYS.iter().map(|key| key.parse().unwrap()).collect()
+    }
+
+    fn syscall() -> Syscall {
+        Syscall::new(
+            &U256::zero(),
+            &Address::repeat_byte(0x42),
+            &U256::zero(),
+            &U64::from(1_700_000_000u64),
+            &[],
+            &[],
+        )
+    }
+
+    fn signers(syscall: &Syscall) -> Vec<Address> {
+        let digest = syscall_digest(syscall, &U256::one()).unwrap();
+        syscall
+            .signatures()
+            .iter()
+            .map(|sig| recover_signer(sig, &digest).unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_sign_syscall_ascending() {
+        let signed = sign_syscall(&syscall(), &U256::one(), &wallets()).await.unwrap();
+        let addresses = signers(&signed);
+
+        assert_eq!(addresses.len(), 3);
+        assert!(addresses.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[tokio::test]
+    async fn test_sign_syscall_deduplicates() {
+        let mut wallets = wallets();
+        wallets.push(wallets[0].clone());
+
+        let signed = sign_syscall(&syscall(), &U256::one(), &wallets).await.unwrap();
+
+        assert_eq!(signed.signatures().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_sign_syscall_merges() {
+        let wallets = wallets();
+        let first = sign_syscall(&syscall(), &U256::one(), &wallets[..2]).await.unwrap();
+        let merged = sign_syscall(&first, &U256::one(), &wallets[1..]).await.unwrap();
+        let all = sign_syscall(&syscall(), &U256::one(), &wallets).await.unwrap();
+
+        assert_eq!(signers(&merged), signers(&all));
+    }
+
+    #[tokio::test]
+    async fn test_sign_syscall_v_first() {
+        let signed = sign_syscall(&syscall(), &U256::one(), &wallets()[..1]).await.unwrap();
+        let v = signed.signatures()[0].to_bytes()[0];
+
+        assert!(v == 27 || v == 28);
+    }
+}
@@ -0,0 +1,644 @@
+//! # Ether Deck Simulation Module
+//!
+//! Contains an in-memory EVM for executing the deck's bytecode offline.
+//!
+//! Transactions are executed under Cancun rules with a zero gas price, so callers need no balance
+//! beyond the value they send. Each transaction is committed before the next.
+use crate::{
+    deploy::ETHER_DECK_BYTECODE,
+    error::Error,
+    trace::{annotate, Step, Trace},
+    util::u256_to_be,
+};
+use ethers::prelude::{Address, Log, H256, U256};
+use revm::{
+    inspector_handle_register,
+    interpreter::{opcode::OpCode, Interpreter},
+    primitives::{self, AccountInfo, Bytecode, ExecutionResult, Output, SpecId, TxKind},
+    Database, DatabaseCommit, DatabaseRef, Evm, EvmContext, InMemoryDB, Inspector,
+};
+
+/// ## Simulation Status Enum
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Status {
+    /// The transaction succeeded.
+    Success,
+    /// The transaction reverted; the outcome's output is the revert data.
+    Revert,
+    /// The transaction halted exceptionally, consuming all gas.
+    Halt(String),
+}
+
+/// ## Storage Diff
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StorageDiff {
+    account: Address,
+    slot: H256,
+    from: H256,
+    to: H256,
+}
+
+impl StorageDiff {
+    /// ### Create New Storage Diff
+    ///
+    /// #### Parameters
+    ///
+    /// - `account`: The account whose storage changed.
+    /// - `slot`: The slot.
+    /// - `from`: The value before the transaction.
+    /// - `to`: The value after the transaction.
+    pub fn new(account: &Address, slot: &H256, from: &H256, to: &H256) -> Self {
+        Self {
+            account: *account,
+            slot: *slot,
+            from: *from,
+            to: *to,
+        }
+    }
+
+    /// ### Get the account
+    pub fn account(&self) -> &Address {
+        &self.account
+    }
+
+    /// ### Get the slot
+    pub fn slot(&self) -> &H256 {
+        &self.slot
+    }
+
+    /// ### Get the value before the transaction
+    pub fn from(&self) -> &H256 {
+        &self.from
+    }
+
+    /// ### Get the value after the transaction
+    pub fn to(&self) -> &H256 {
+        &self.to
+    }
+}
+
+/// ## Simulation Outcome
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Outcome {
+    status: Status,
+    output: Vec<u8>,
+    logs: Vec<Log>,
+    gas_used: u64,
+    storage: Vec<StorageDiff>,
+}
+
+impl Outcome {
+    /// ### Get the status
+    pub fn status(&self) -> &Status {
+        &self.status
+    }
+
+    /// ### Get whether the transaction succeeded
+    pub fn is_success(&self) -> bool {
+        self.status == Status::Success
+    }
+
+    /// ### Get the return or revert data
+    pub fn output(&self) -> &[u8] {
+        &self.output
+    }
+
+    /// ### Get the logs, in emission order
+    pub fn logs(&self) -> &[Log] {
+        &self.logs
+    }
+
+    /// ### Get the gas used, including the intrinsic gas
+    pub fn gas_used(&self) -> u64 {
+        self.gas_used
+    }
+
+    /// ### Get the storage changes, ordered by account and slot
+    pub fn storage(&self) -> &[StorageDiff] {
+        &self.storage
+    }
+}
+
+/// ## Simulator
+#[derive(Debug, Clone)]
+pub struct Simulator {
+    db: InMemoryDB,
+    chain_id: u64,
+    timestamp: u64,
+}
+
+impl Default for Simulator {
+    fn default() -> Self {
+        Self::new(1)
+    }
+}
+
+impl Simulator {
+    /// ### Create New Simulator
+    ///
+    /// The block timestamp starts at one.
+    ///
+    /// #### Parameters
+    ///
+    /// - `chain_id`: The chain id, as read by the deck's digest.
+    pub fn new(chain_id: u64) -> Self {
+        Self {
+            db: InMemoryDB::default(),
+            chain_id,
+            timestamp: 1,
+        }
+    }
+
+    /// ### Get the chain id
+    pub fn chain_id(&self) -> u64 {
+        self.chain_id
+    }
+
+    /// ### Get the block timestamp
+    pub fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+
+    /// ### Set the block timestamp
+    pub fn set_timestamp(&mut self, timestamp: u64) {
+        self.timestamp = timestamp;
+    }
+
+    /// ### Set an account's balance
+    pub fn set_balance(&mut self, account: &Address, balance: &U256) {
+        let mut info = self.info(account);
+        info.balance = primitives::U256::from_be_bytes(u256_to_be(balance));
+        self.db.insert_account_info(to_address(account), info);
+    }
+
+    /// ### Get an account's balance
+    pub fn balance(&self, account: &Address) -> U256 {
+        U256::from_big_endian(&self.info(account).balance.to_be_bytes::<32>())
+    }
+
+    /// ### Set an account's code, such as a shard's
+    pub fn set_code(&mut self, account: &Address, code: &[u8]) {
+        let bytecode = Bytecode::new_raw(code.to_vec().into());
+        let mut info = self.info(account);
+        info.code_hash = bytecode.hash_slow();
+        info.code = Some(bytecode);
+        self.db.insert_account_info(to_address(account), info);
+    }
+
+    /// ### Get an account's code
+    pub fn code(&self, account: &Address) -> Vec<u8> {
+        match self.info(account).code {
+            Some(code) => code.original_bytes().to_vec(),
+            None => vec![],
+        }
+    }
+
+    /// ### Read a storage slot
+    pub fn storage(&self, account: &Address, slot: &[u8; 32]) -> H256 {
+        let value = self
+            .db
+            .storage_ref(to_address(account), primitives::U256::from_be_bytes(*slot))
+            .unwrap_or_default();
+        H256(value.to_be_bytes())
+    }
+
+    /// ### Write a storage slot, such as to authorize an account without governance
+    pub fn set_storage(&mut self, account: &Address, slot: &[u8; 32], value: &H256) {
+        self.db
+            .insert_account_storage(
+                to_address(account),
+                primitives::U256::from_be_bytes(*slot),
+                primitives::U256::from_be_bytes(value.0),
+            )
+            .expect("the empty database is infallible");
+    }
+
+    /// ### Deploy the deck
+    ///
+    /// #### Parameters
+    ///
+    /// - `deployer`: The deployer, authorized by the constructor.
+    ///
+    /// #### Returns
+    ///
+    /// Either the deck's address or an [Error](crate::error::Error).
+    pub fn deploy(&mut self, deployer: &Address) -> Result<Address, Error> {
+        let (result, _, _) = self.execute(
+            deployer,
+            TxKind::Create,
+            &U256::zero(),
+            &ETHER_DECK_BYTECODE,
+            false,
+        )?;
+
+        match result {
+            ExecutionResult::Success {
+                output: Output::Create(_, Some(address)),
+                ..
+            } => Ok(Address::from_slice(address.as_slice())),
+            result => Err(Error::Simulation(format!(
+                "Deployment failed: {:?}",
+                result
+            ))),
+        }
+    }
+
+    /// ### Call an account
+    ///
+    /// #### Parameters
+    ///
+    /// - `caller`: The transaction sender.
+    /// - `to`: The account to call, such as the deck.
+    /// - `value`: The call value.
+    /// - `calldata`: The calldata, such as an [encoded](crate::coder::encoder::encode) call.
+    ///
+    /// #### Returns
+    ///
+    /// Either the [Outcome] or an [Error](crate::error::Error) if the transaction is invalid.
+    pub fn call(
+        &mut self,
+        caller: &Address,
+        to: &Address,
+        value: &U256,
+        calldata: &[u8],
+    ) -> Result<Outcome, Error> {
+        Ok(self.run(caller, to, value, calldata, false)?.0)
+    }
+
+    /// ### Call an account and trace the deck's execution
+    ///
+    /// #### Parameters
+    ///
+    /// - `caller`: The transaction sender.
+    /// - `to`: The deck.
+    /// - `value`: The call value.
+    /// - `calldata`: The calldata.
+    ///
+    /// #### Returns
+    ///
+    /// Either the [Outcome] and the annotated [Trace] or an [Error](crate::error::Error) if the
+    /// transaction is invalid.
+    pub fn trace(
+        &mut self,
+        caller: &Address,
+        to: &Address,
+        value: &U256,
+        calldata: &[u8],
+    ) -> Result<(Outcome, Trace), Error> {
+        let (outcome, steps) = self.run(caller, to, value, calldata, true)?;
+        Ok((outcome, annotate(steps)))
+    }
+
+    /// Executes a call, recording its steps if tracing.
+    fn run(
+        &mut self,
+        caller: &Address,
+        to: &Address,
+        value: &U256,
+        calldata: &[u8],
+        trace: bool,
+    ) -> Result<(Outcome, Vec<Step>), Error> {
+        let (result, storage, steps) =
+            self.execute(caller, TxKind::Call(to_address(to)), value, calldata, trace)?;
+
+        let (status, output, logs, gas_used) = match result {
+            ExecutionResult::Success {
+                logs,
+                output,
+                gas_used,
+                ..
+            } => (Status::Success, output.into_data().to_vec(), logs, gas_used),
+            ExecutionResult::Revert { output, gas_used } => {
+                (Status::Revert, output.to_vec(), vec![], gas_used)
+            }
+            ExecutionResult::Halt { reason, gas_used } => (
+                Status::Halt(format!("{:?}", reason)),
+                vec![],
+                vec![],
+                gas_used,
+            ),
+        };
+
+        let outcome = Outcome {
+            status,
+            output,
+            logs: logs.iter().map(to_log).collect(),
+            gas_used,
+            storage,
+        };
+        Ok((outcome, steps))
+    }
+
+    /// Executes and commits a transaction, returning its result, storage changes, and steps.
+    fn execute(
+        &mut self,
+        caller: &Address,
+        kind: TxKind,
+        value: &U256,
+        data: &[u8],
+        trace: bool,
+    ) -> Result<(ExecutionResult, Vec<StorageDiff>, Vec<Step>), Error> {
+        let (chain_id, timestamp) = (self.chain_id, self.timestamp);
+
+        let builder = Evm::builder()
+            .with_db(&mut self.db)
+            .with_spec_id(SpecId::CANCUN)
+            .modify_cfg_env(|cfg| cfg.chain_id = chain_id)
+            .modify_block_env(|block| block.timestamp = primitives::U256::from(timestamp))
+            .modify_tx_env(|tx| {
+                tx.caller = to_address(caller);
+                tx.transact_to = kind;
+                tx.value = primitives::U256::from_be_bytes(u256_to_be(value));
+                tx.data = data.to_vec().into();
+                tx.gas_limit = 30_000_000;
+            });
+
+        // the inspector handler is only registered when tracing
+        let (result, steps) = match trace {
+            true => {
+                let mut evm = builder
+                    .with_external_context(StepRecorder::default())
+                    .append_handler_register(inspector_handle_register)
+                    .build();
+                let result = evm.transact();
+                (result, std::mem::take(&mut evm.context.external.steps))
+            }
+            false => (builder.build().transact(), Vec::new()),
+        };
+        let result = result.map_err(|e| Error::Simulation(e.to_string()))?;
+
+        let mut storage = result
+            .state
+            .iter()
+            .flat_map(|(account, state)| {
+                state
+                    .storage
+                    .iter()
+                    .filter(|(_, slot)| slot.is_changed())
+                    .map(move |(slot, value)| {
+                        StorageDiff::new(
+                            &Address::from_slice(account.as_slice()),
+                            &H256(slot.to_be_bytes()),
+                            &H256(value.original_value.to_be_bytes()),
+                            &H256(value.present_value.to_be_bytes()),
+                        )
+                    })
+            })
+            .collect::<Vec<StorageDiff>>();
+        storage.sort_by_key(|diff| (diff.account, diff.slot));
+
+        self.db.commit(result.state);
+        Ok((result.result, storage, steps))
+    }
+
+    /// Returns an account's info, default if the account does not exist.
+    fn info(&self, account: &Address) -> AccountInfo {
+        self.db
+            .basic_ref(to_address(account))
+            .ok()
+            .flatten()
+            .unwrap_or_default()
+    }
+}
+
+/// Records each step.
+#[derive(Default)]
+struct StepRecorder {
+    steps: Vec<Step>,
+}
+
+impl<DB: Database> Inspector<DB> for StepRecorder {
+    fn step(&mut self, interp: &mut Interpreter, context: &mut EvmContext<DB>) {
+        let opcode = interp.current_opcode();
+        let op = match OpCode::new(opcode) {
+            Some(op) => op.as_str().to_string(),
+            None => format!("0x{:02x}", opcode),
+        };
+        let stack = interp
+            .stack
+            .data()
+            .iter()
+            .map(|word| U256::from_big_endian(&word.to_be_bytes::<32>()))
+            .collect();
+
+        self.steps.push(Step::new(
+            interp.program_counter() as u64,
+            &op,
+            context.journaled_state.depth(),
+            interp.gas().remaining(),
+            stack,
+        ));
+    }
+}
+
+fn to_address(address: &Address) -> primitives::Address {
+    primitives::Address::from(address.0)
+}
+
+fn to_log(log: &primitives::Log) -> Log {
+    Log {
+        address: Address::from_slice(log.address.as_slice()),
+        topics: log
+            .data
+            .topics()
+            .iter()
+            .map(|topic| H256(topic.0))
+            .collect(),
+        data: log.data.data.to_vec().into(),
+        ..Default::default()
+    }
+}
+
+/// Syscall fixtures shared by tests that execute the deck in the simulator.
+#[cfg(test)]
+pub(crate) mod fixture {
+    use super::*;
+    use crate::{
+        coder::{
+            encoder::encode,
+            structures::{Encoding, Syscall},
+        },
+        sign::sign_syscall,
+        storage::slot::{auth_slot, id_slot, threshold_slot},
+    };
+    use ethers::{prelude::H160, signers::LocalWallet};
+
+    /// Logs its calldata with `LOG0`.
+    pub(crate) const RECORDER: [u8; 10] =
+        [0x36, 0x60, 0x00, 0x80, 0x37, 0x36, 0x60, 0x00, 0xa0, 0x00];
+
+    /// Submits every fixture syscall.
+    pub(crate) const RELAYER: Address = H160([1; 20]);
+
+    /// Deploys a deck, then authorizes the owners and sets the threshold and id.
+    pub(crate) fn deck_with_owners(
+        sim: &mut Simulator,
+        deployer: &Address,
+        owners: &[Address],
+        threshold: u8,
+        id: &U256,
+    ) -> Address {
+        let deck = sim.deploy(deployer).unwrap();
+        for owner in owners {
+            sim.set_storage(&deck, &auth_slot(owner), &H256::from_low_u64_be(1));
+        }
+        let threshold = H256::from_low_u64_be(threshold as u64);
+        sim.set_storage(&deck, &u256_to_be(&threshold_slot()), &threshold);
+        sim.set_storage(&deck, &u256_to_be(&id_slot()), &H256(u256_to_be(id)));
+        deck
+    }
+
+    /// Signs a syscall for the simulator's chain and submits it to the deck from the relayer,
+    /// returning the signed syscall and the outcome.
+    pub(crate) async fn execute_syscall(
+        sim: &mut Simulator,
+        deck: &Address,
+        syscall: &Syscall,
+        signers: &[LocalWallet],
+    ) -> (Syscall, Outcome) {
+        let signed = sign_syscall(syscall, &U256::from(sim.chain_id()), signers).await.unwrap();
+        let calldata = encode(&Encoding::Syscall(signed.clone())).unwrap();
+        let outcome = sim.call(&RELAYER, deck, &U256::zero(), &calldata).unwrap();
+        (signed, outcome)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fixture::execute_syscall, *};
+    use crate::{
+        coder::encoder::{encode_set_threshold, encode_syscall},
+        events::{decode_log, DeckEvent, SyscallEvent, ThresholdSetEvent},
+        governance::change_threshold,
+        revert::decode_revert,
+        sign::sign_syscall,
+        storage::slot::{auth_slot, shard_slot, threshold_slot},
+    };
+    use ethers::{prelude::U64, signers::LocalWallet, signers::Signer};
+
+    fn wallet() -> LocalWallet {
+        "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80"
+            .parse::<LocalWallet>()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_deploy() {
+        let mut sim = Simulator::default();
+        let deployer = Address::repeat_byte(9);
+        let deck = sim.deploy(&deployer).unwrap();
+
+        assert_eq!(sim.code(&deck), &ETHER_DECK_BYTECODE[0x1f..]);
+        assert_eq!(
+            sim.storage(&deck, &auth_slot(&deployer)),
+            H256::from_low_u64_be(1)
+        );
+        assert_eq!(
+            sim.storage(&deck, &u256_to_be(&threshold_slot())),
+            H256::from_low_u64_be(1)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_governance_syscall() {
+        let mut sim = Simulator::new(31337);
+        let signer = wallet();
+        let deck = sim.deploy(&signer.address()).unwrap();
+
+        let proposal = change_threshold(&deck, &U256::zero(), 1, &U64::from(100)).unwrap();
+        let chain_id = U256::from(sim.chain_id());
+        let signed = sign_syscall(&proposal, &chain_id, &[signer]).await.unwrap();
+        let calldata = encode_syscall(
+            signed.id(),
+            signed.target(),
+            signed.value(),
+            signed.deadline(),
+            signed.payload(),
+            signed.signatures(),
+        )
+        .unwrap();
+
+        let outcome = sim
+            .call(&Address::repeat_byte(1), &deck, &U256::zero(), &calldata)
+            .unwrap();
+
+        assert!(outcome.is_success());
+        assert!(outcome.gas_used() > 21_000);
+        assert_eq!(
+            outcome
+                .logs()
+                .iter()
+                .map(|log| decode_log(log).unwrap())
+                .collect::<Vec<DeckEvent>>(),
+            vec![
+                DeckEvent::Syscall(SyscallEvent::new(&U256::zero())),
+                DeckEvent::ThresholdSet(ThresholdSetEvent::new(1)),
+            ]
+        );
+        // the id is incremented; the threshold is rewritten with the same value
+        assert_eq!(
+            outcome.storage(),
+            &[StorageDiff::new(
+                &deck,
+                &H256::zero(),
+                &H256::zero(),
+                &H256::from_low_u64_be(1)
+            )]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_deadline_expired() {
+        let mut sim = Simulator::new(31337);
+        let signer = wallet();
+        let deck = sim.deploy(&signer.address()).unwrap();
+        sim.set_timestamp(100);
+
+        let proposal = change_threshold(&deck, &U256::zero(), 1, &U64::from(100)).unwrap();
+        let (_, outcome) = execute_syscall(&mut sim, &deck, &proposal, &[signer]).await;
+
+        assert_eq!(decode_revert(outcome.output()), Error::DeadlineExpired);
+        assert_eq!(sim.storage(&deck, &[0; 32]), H256::zero());
+    }
+
+    #[test]
+    fn test_gated_call_reverts() {
+        let mut sim = Simulator::default();
+        let deck = sim.deploy(&Address::repeat_byte(9)).unwrap();
+
+        let outcome = sim
+            .call(
+                &Address::repeat_byte(9),
+                &deck,
+                &U256::zero(),
+                &encode_set_threshold(2),
+            )
+            .unwrap();
+
+        assert_eq!(outcome.status(), &Status::Revert);
+        assert_eq!(decode_revert(outcome.output()), Error::Unauthorized);
+        assert!(outcome.storage().is_empty());
+    }
+
+    #[test]
+    fn test_shard_fallback() {
+        let mut sim = Simulator::default();
+        let deck = sim.deploy(&Address::repeat_byte(9)).unwrap();
+        let caller = Address::repeat_byte(1);
+
+        let outcome = sim.call(&caller, &deck, &U256::zero(), &[1; 4]).unwrap();
+        assert_eq!(decode_revert(outcome.output()), Error::ShardNotFound);
+
+        // install a shard returning 0x2a, bypassing governance
+        let shard = Address::repeat_byte(0x5d);
+        sim.set_code(
+            &shard,
+            &[0x60, 0x2a, 0x60, 0x00, 0x53, 0x60, 0x01, 0x60, 0x00, 0xf3],
+        );
+        sim.set_storage(&deck, &shard_slot(&[1; 4]), &H256::from(shard));
+
+        let outcome = sim.call(&caller, &deck, &U256::zero(), &[1; 4]).unwrap();
+        assert!(outcome.is_success());
+        assert_eq!(outcome.output(), &[0x2a]);
+    }
+}
@@ -0,0 +1,220 @@
+//! # Ether Deck Storage Batch Module
+//!
+//! Contains a batched reader for the deck's storage.
+//!
+//! Every slot is read in a single `eth_call` to the deck with its code overridden by a storage
+//! reader. The override keeps the deck's storage, so the reader sees the deck's slots. Not every
+//! node supports state overrides; the [reader](crate::storage::reader) functions remain the
+//! portable path.
+use crate::{
+    error::Error,
+    storage::slot::{auth_slot, id_slot, shard_slot, threshold_slot},
+    util::u256_to_be,
+};
+use ethers::{prelude::*, types::transaction::eip2718::TypedTransaction};
+
+/// ## Storage Reader Bytecode
+///
+/// Loads each 32 byte calldata word as a slot and returns the slots' values, in order.
+///
+/// ```text
+/// PUSH1 0x00
+/// JUMPDEST         ; loop
+/// DUP1
+/// CALLDATALOAD
+/// SLOAD
+/// DUP2
+/// MSTORE
+/// PUSH1 0x20
+/// ADD
+/// DUP1
+/// CALLDATASIZE
+/// GT
+/// PUSH1 0x02       ; loop
+/// JUMPI
+/// CALLDATASIZE
+/// PUSH1 0x00
+/// RETURN
+/// ```
+pub const STORAGE_READER_BYTECODE: [u8; 21] = [
+    0x60, 0x00, 0x5b, 0x80, 0x35, 0x54, 0x81, 0x52, 0x60, 0x20, 0x01, 0x80, 0x36, 0x11, 0x60,
+    0x02, 0x57, 0x36, 0x60, 0x00, 0xf3,
+];
+
+/// ## Slot Query Enum
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SlotQuery {
+    /// Syscall ID
+    Id,
+    /// Threshold
+    Threshold,
+    /// Authorization of an account
+    Auth(Address),
+    /// Shard of a selector
+    Shard([u8; 4]),
+}
+
+impl SlotQuery {
+    /// ### Get the storage slot
+    pub fn slot(&self) -> [u8; 32] {
+        match self {
+            SlotQuery::Id => u256_to_be(&id_slot()),
+            SlotQuery::Threshold => u256_to_be(&threshold_slot()),
+            SlotQuery::Auth(account) => auth_slot(account),
+            SlotQuery::Shard(selector) => shard_slot(selector),
+        }
+    }
+
+    /// ### Decode the slot's value
+    ///
+    /// #### Parameters
+    ///
+    /// - `word`: The slot's 32 byte value.
+    pub fn decode(&self, word: &[u8; 32]) -> SlotValue {
+        match self {
+            SlotQuery::Id => SlotValue::Id(U256::from(word)),
+            SlotQuery::Threshold => SlotValue::Threshold(word[31]),
+            SlotQuery::Auth(account) => SlotValue::Auth(*account, word[31] == 1),
+            SlotQuery::Shard(selector) => {
+                let shard = Some(Address::from(H256(*word))).filter(|shard| !shard.is_zero());
+                SlotValue::Shard(*selector, shard)
+            }
+        }
+    }
+}
+
+/// ## Slot Value Enum
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SlotValue {
+    /// Syscall ID
+    Id(U256),
+    /// Threshold
+    Threshold(u8),
+    /// Authorization of an account
+    Auth(Address, bool),
+    /// Shard of a selector, `None` if not registered
+    Shard([u8; 4], Option<Address>),
+}
+
+/// ## Read Batch
+///
+/// ### Parameters
+///
+/// - `provider`: The provider to read the deck with. Must support `eth_call` state overrides.
+/// - `deck`: The deck's address.
+/// - `queries`: The slots to read.
+/// - `block`: The block to read at, `latest` if `None`.
+///
+/// ### Returns
+///
+/// Either a [SlotValue] for each query, in order, or an [Error](crate::error::Error).
+pub async fn read_batch<M>(
+    provider: M,
+    deck: &Address,
+    queries: &[SlotQuery],
+    block: Option<BlockId>,
+) -> Result<Vec<SlotValue>, Error>
+where
+    M: Middleware + 'static,
+{
+    if queries.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let calldata = queries.iter().flat_map(SlotQuery::slot).collect::<Vec<u8>>();
+    let tx: TypedTransaction = TransactionRequest::new().to(*deck).data(calldata).into();
+    let state = spoof::code(*deck, Bytes::from(STORAGE_READER_BYTECODE.to_vec()));
+
+    let mut call = provider.provider().call_raw(&tx).state(&state);
+    if let Some(block) = block {
+        call = call.block(block);
+    }
+    let words = call.await.map_err(|e| Error::Middleware(e.to_string()))?;
+
+    if words.len() != queries.len() * 32 {
+        return Err(Error::Middleware("Failed to read storage batch".to_string()));
+    }
+
+    Ok(queries
+        .iter()
+        .zip(words.chunks_exact(32))
+        .map(|(query, word)| query.decode(&word.try_into().expect("32 byte chunk")))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_query_slots() {
+        let account = Address::repeat_byte(1);
+
+        assert_eq!(SlotQuery::Id.slot(), [0; 32]);
+        assert_eq!(SlotQuery::Threshold.slot()[31], 1);
+        assert_eq!(SlotQuery::Auth(account).slot(), auth_slot(&account));
+        assert_eq!(SlotQuery::Shard([1; 4]).slot(), shard_slot(&[1; 4]));
+    }
+
+    #[tokio::test]
+    async fn test_read_batch() {
+        let deck = Address::repeat_byte(0xde);
+        let account = Address::repeat_byte(1);
+        let queries = [
+            SlotQuery::Id,
+            SlotQuery::Threshold,
+            SlotQuery::Auth(account),
+            SlotQuery::Shard([1; 4]),
+        ];
+
+        let mut words = Vec::new();
+        words.extend_from_slice(H256::from_low_u64_be(5).as_bytes());
+        words.extend_from_slice(H256::from_low_u64_be(2).as_bytes());
+        words.extend_from_slice(H256::from_low_u64_be(1).as_bytes());
+        words.extend_from_slice(H256::from(Address::repeat_byte(0xa)).as_bytes());
+
+        let (provider, mock) = Provider::mocked();
+        mock.push::<Bytes, _>(Bytes::from(words)).unwrap();
+
+        let values = read_batch(provider, &deck, &queries, None).await.unwrap();
+
+        assert_eq!(
+            values,
+            vec![
+                SlotValue::Id(U256::from(5)),
+                SlotValue::Threshold(2),
+                SlotValue::Auth(account, true),
+                SlotValue::Shard([1; 4], Some(Address::repeat_byte(0xa))),
+            ]
+        );
+
+        let calldata = queries.iter().flat_map(SlotQuery::slot).collect::<Vec<u8>>();
+        let tx: TypedTransaction = TransactionRequest::new().to(deck).data(calldata).into();
+        let state = spoof::code(deck, Bytes::from(STORAGE_READER_BYTECODE.to_vec()));
+        mock.assert_request("eth_call", (tx, BlockId::from(BlockNumber::Latest), state))
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_read_batch_length_mismatch() {
+        let (provider, mock) = Provider::mocked();
+        mock.push::<Bytes, _>(Bytes::from(vec![0; 32])).unwrap();
+
+        let result = read_batch(
+            provider,
+            &Address::zero(),
+            &[SlotQuery::Id, SlotQuery::Threshold],
+            None,
+        )
+        .await;
+
+        assert!(matches!(result, Err(Error::Middleware(_))));
+    }
+
+    #[tokio::test]
+    async fn test_read_batch_empty() {
+        let (provider, _) = Provider::mocked();
+
+        assert_eq!(read_batch(provider, &Address::zero(), &[], None).await, Ok(vec![]));
+    }
+}
@@ -1,2 +1,5 @@
+pub mod batch;
+pub mod proof;
 pub mod reader;
 pub mod slot;
+pub mod state;
@@ -0,0 +1,471 @@
+//! # Ether Deck Storage Proof Module
+//!
+//! Contains a reader that verifies the deck's storage with `eth_getProof` against a trusted state
+//! root, so the values can be trusted without trusting the provider.
+use crate::{
+    error::Error,
+    storage::{
+        batch::{SlotQuery, SlotValue},
+        state::DeckState,
+    },
+};
+use ethers::{
+    prelude::*,
+    utils::{
+        keccak256,
+        rlp::{DecoderError, Rlp},
+    },
+};
+use std::collections::BTreeMap;
+
+/// ## Empty Trie Root
+///
+/// `keccak256(rlp(""))`, the root of an empty trie.
+pub const EMPTY_TRIE_ROOT: [u8; 32] = [
+    0x56, 0xe8, 0x1f, 0x17, 0x1b, 0xcc, 0x55, 0xa6, 0xff, 0x83, 0x45, 0xe6, 0x92, 0xc0, 0xf8, 0x6e,
+    0x5b, 0x48, 0xe0, 0x1b, 0x99, 0x6c, 0xad, 0xc0, 0x01, 0x62, 0x2f, 0xb5, 0xe3, 0x63, 0xb4, 0x21,
+];
+
+/// ## Verify Proof
+///
+/// Walks a Merkle-Patricia proof from `root` along `keccak256(key)`. Nodes shorter than 32 bytes
+/// are embedded in their parent and are not part of the proof.
+///
+/// ### Parameters
+///
+/// - `root`: The trusted trie root.
+/// - `key`: The unhashed key.
+/// - `proof`: The proof nodes, root first.
+///
+/// ### Returns
+///
+/// Either the key's RLP encoded value, `None` if the proof shows the key is absent, or an
+/// [Error](crate::error::Error) if the proof does not verify.
+pub fn verify_proof(root: &H256, key: &[u8], proof: &[Bytes]) -> Result<Option<Vec<u8>>, Error> {
+    if root.0 == EMPTY_TRIE_ROOT {
+        return Ok(None);
+    }
+
+    let path = keccak256(key)
+        .iter()
+        .flat_map(|byte| [byte >> 4, byte & 0x0f])
+        .collect::<Vec<u8>>();
+    let mut path = &path[..];
+    let mut proof = proof.iter();
+    let mut node = next_node(&mut proof, &root.0)?;
+
+    loop {
+        let rlp = Rlp::new(&node);
+        let child = match rlp.item_count().map_err(malformed)? {
+            17 => match path.split_first() {
+                Some((nibble, rest)) => {
+                    path = rest;
+                    rlp.at(*nibble as usize).map_err(malformed)?
+                }
+                None => return value(&rlp.at(16).map_err(malformed)?),
+            },
+            2 => {
+                let (nibbles, leaf) = decode_hex_prefix(rlp.at(0).map_err(malformed)?.data())?;
+                if leaf {
+                    return match path == nibbles {
+                        true => value(&rlp.at(1).map_err(malformed)?),
+                        false => Ok(None),
+                    };
+                }
+                if !path.starts_with(&nibbles) {
+                    return Ok(None);
+                }
+                path = &path[nibbles.len()..];
+                rlp.at(1).map_err(malformed)?
+            }
+            _ => return Err(Error::InvalidProof("unexpected node".to_string())),
+        };
+
+        let next = if child.is_list() {
+            child.as_raw().to_vec()
+        } else {
+            match child.data().map_err(malformed)? {
+                [] => return Ok(None),
+                hash => next_node(&mut proof, hash)?,
+            }
+        };
+        node = next;
+    }
+}
+
+/// ## Verify Account Proof
+///
+/// ### Parameters
+///
+/// - `state_root`: The trusted state root.
+/// - `response`: The `eth_getProof` response.
+///
+/// ### Returns
+///
+/// Either the verified storage root of the account, [EMPTY_TRIE_ROOT] if the account does not
+/// exist, or an [Error](crate::error::Error).
+pub fn verify_account_proof(
+    state_root: &H256,
+    response: &EIP1186ProofResponse,
+) -> Result<H256, Error> {
+    let storage_root = match verify_proof(
+        state_root,
+        response.address.as_bytes(),
+        &response.account_proof,
+    )? {
+        Some(account) => {
+            let account = Rlp::new(&account);
+            let root = account.at(2).and_then(|root| root.data()).map_err(malformed)?;
+            if account.item_count().map_err(malformed)? != 4 || root.len() != 32 {
+                return Err(Error::InvalidProof("malformed account".to_string()));
+            }
+            H256::from_slice(root)
+        }
+        None => H256(EMPTY_TRIE_ROOT),
+    };
+
+    if storage_root != response.storage_hash {
+        return Err(Error::InvalidProof(format!(
+            "storage root is {:?}, expected {:?}",
+            response.storage_hash, storage_root
+        )));
+    }
+
+    Ok(storage_root)
+}
+
+/// ## Verify Storage Proof
+///
+/// ### Parameters
+///
+/// - `storage_root`: The verified storage root.
+/// - `slot`: The slot the proof is expected to be for.
+/// - `proof`: The slot's storage proof.
+///
+/// ### Returns
+///
+/// Either the verified value of the slot or an [Error](crate::error::Error).
+pub fn verify_storage_proof(
+    storage_root: &H256,
+    slot: &[u8; 32],
+    proof: &StorageProof,
+) -> Result<H256, Error> {
+    if proof.key != U256::from(slot) {
+        return Err(Error::InvalidProof("unexpected storage key".to_string()));
+    }
+
+    let value = match verify_proof(storage_root, slot, &proof.proof)? {
+        Some(value) => {
+            let value = Rlp::new(&value).data().map_err(malformed)?.to_vec();
+            if value.len() > 32 {
+                return Err(Error::InvalidProof("malformed storage value".to_string()));
+            }
+            U256::from_big_endian(&value)
+        }
+        None => U256::zero(),
+    };
+
+    if value != proof.value {
+        return Err(Error::InvalidProof(format!(
+            "storage value is {}, expected {}",
+            proof.value, value
+        )));
+    }
+
+    let mut word = H256::zero();
+    value.to_big_endian(word.as_bytes_mut());
+    Ok(word)
+}
+
+/// ## Read Proven State
+///
+/// ### Parameters
+///
+/// - `provider`: The untrusted provider to read the proofs with.
+/// - `deck`: The deck's address.
+/// - `accounts`: The accounts to read authorization for.
+/// - `selectors`: The selectors to read shards for.
+/// - `block`: The trusted block hash.
+/// - `state_root`: The trusted state root of the block.
+///
+/// ### Returns
+///
+/// Either the verified [DeckState] or an [Error](crate::error::Error).
+pub async fn read_proven_state<M>(
+    provider: M,
+    deck: &Address,
+    accounts: &[Address],
+    selectors: &[[u8; 4]],
+    block: &H256,
+    state_root: &H256,
+) -> Result<DeckState, Error>
+where
+    M: Middleware + 'static,
+{
+    let queries = [SlotQuery::Id, SlotQuery::Threshold]
+        .into_iter()
+        .chain(accounts.iter().map(|account| SlotQuery::Auth(*account)))
+        .chain(selectors.iter().map(|selector| SlotQuery::Shard(*selector)))
+        .collect::<Vec<SlotQuery>>();
+    let slots = queries
+        .iter()
+        .map(|query| H256(query.slot()))
+        .collect::<Vec<H256>>();
+
+    let response = provider
+        .get_proof(*deck, slots, Some(BlockId::Hash(*block)))
+        .await
+        .map_err(|e| Error::Middleware(e.to_string()))?;
+
+    if response.address != *deck || response.storage_proof.len() != queries.len() {
+        return Err(Error::InvalidProof("unexpected proof response".to_string()));
+    }
+
+    let storage_root = verify_account_proof(state_root, &response)?;
+
+    let (mut id, mut threshold) = (U256::zero(), 0);
+    let (mut auths, mut shards) = (BTreeMap::new(), BTreeMap::new());
+
+    for (query, proof) in queries.iter().zip(&response.storage_proof) {
+        let word = verify_storage_proof(&storage_root, &query.slot(), proof)?;
+        match query.decode(&word.0) {
+            SlotValue::Id(value) => id = value,
+            SlotValue::Threshold(value) => threshold = value,
+            SlotValue::Auth(account, authorized) => {
+                auths.insert(account, authorized);
+            }
+            SlotValue::Shard(selector, shard) => {
+                shards.insert(selector, shard);
+            }
+        }
+    }
+
+    Ok(DeckState::new(block, &id, threshold, auths, shards))
+}
+
+/// Takes the next proof node, checking it hashes to `hash`.
+fn next_node<'a>(
+    proof: &mut impl Iterator<Item = &'a Bytes>,
+    hash: &[u8],
+) -> Result<Vec<u8>, Error> {
+    let node = proof
+        .next()
+        .ok_or(Error::InvalidProof("proof ends early".to_string()))?;
+
+    if keccak256(node) != hash {
+        return Err(Error::InvalidProof("node hash mismatch".to_string()));
+    }
+
+    Ok(node.to_vec())
+}
+
+/// Returns a node's value, `None` if empty.
+fn value(rlp: &Rlp) -> Result<Option<Vec<u8>>, Error> {
+    match rlp.data().map_err(malformed)? {
+        [] => Ok(None),
+        value => Ok(Some(value.to_vec())),
+    }
+}
+
+/// Decodes a hex prefix encoded path into its nibbles and whether it is a leaf's.
+fn decode_hex_prefix(encoded: Result<&[u8], DecoderError>) -> Result<(Vec<u8>, bool), Error> {
+    let encoded = encoded.map_err(malformed)?;
+    let (first, rest) = encoded
+        .split_first()
+        .ok_or(Error::InvalidProof("empty path".to_string()))?;
+    let flag = first >> 4;
+    if flag > 3 {
+        return Err(Error::InvalidProof("malformed path".to_string()));
+    }
+
+    let mut nibbles = Vec::new();
+    if flag & 1 == 1 {
+        nibbles.push(first & 0x0f);
+    }
+    nibbles.extend(rest.iter().flat_map(|byte| [byte >> 4, byte & 0x0f]));
+
+    Ok((nibbles, flag & 2 == 2))
+}
+
+/// Maps an RLP decoding error to [InvalidProof](crate::error::Error::InvalidProof).
+fn malformed(error: DecoderError) -> Error {
+    Error::InvalidProof(error.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::utils::rlp::{encode, RlpStream};
+
+    fn nibbles(key: &[u8]) -> Vec<u8> {
+        keccak256(key).iter().flat_map(|byte| [byte >> 4, byte & 0x0f]).collect()
+    }
+
+    fn leaf(nibbles: &[u8], value: &[u8]) -> Vec<u8> {
+        let mut path = vec![if nibbles.len() % 2 == 1 { 0x30 | nibbles[0] } else { 0x20 }];
+        let even = &nibbles[nibbles.len() % 2..];
+        path.extend(even.chunks(2).map(|pair| (pair[0] << 4) | pair[1]));
+
+        let mut stream = RlpStream::new_list(2);
+        stream.append(&path).append(&value.to_vec());
+        stream.out().to_vec()
+    }
+
+    fn branch(children: &[(u8, &[u8])]) -> Vec<u8> {
+        let mut stream = RlpStream::new_list(17);
+        for nibble in 0..17 {
+            match children.iter().find(|(n, _)| *n == nibble) {
+                Some((_, child)) => stream.append(&keccak256(child).to_vec()),
+                None => stream.append_empty_data(),
+            };
+        }
+        stream.out().to_vec()
+    }
+
+    /// A deck whose storage holds only `id = 5` and `threshold = 2`.
+    fn fixture(deck: &Address) -> (H256, EIP1186ProofResponse) {
+        let (id_slot, threshold_slot) = (H256::zero(), H256::from_low_u64_be(1));
+        let id_path = nibbles(id_slot.as_bytes());
+        let threshold_path = nibbles(threshold_slot.as_bytes());
+        let id_leaf = leaf(&id_path[1..], &encode(&vec![5u8]));
+        let threshold_leaf = leaf(&threshold_path[1..], &encode(&vec![2u8]));
+        let storage_root_node =
+            branch(&[(id_path[0], &id_leaf), (threshold_path[0], &threshold_leaf)]);
+        let storage_root = H256(keccak256(&storage_root_node));
+
+        let mut account = RlpStream::new_list(4);
+        account
+            .append(&1u64)
+            .append_empty_data()
+            .append(&storage_root.as_bytes().to_vec())
+            .append(&keccak256([0xfe]).to_vec());
+        let account_leaf = leaf(&nibbles(deck.as_bytes()), &account.out());
+        let state_root = H256(keccak256(&account_leaf));
+
+        let response = EIP1186ProofResponse {
+            address: *deck,
+            storage_hash: storage_root,
+            account_proof: vec![account_leaf.into()],
+            storage_proof: vec![
+                StorageProof {
+                    key: U256::zero(),
+                    proof: vec![storage_root_node.clone().into(), id_leaf.into()],
+                    value: U256::from(5),
+                },
+                StorageProof {
+                    key: U256::one(),
+                    proof: vec![storage_root_node.into(), threshold_leaf.into()],
+                    value: U256::from(2),
+                },
+            ],
+            ..Default::default()
+        };
+
+        (state_root, response)
+    }
+
+    #[test]
+    fn test_verify_account_proof() {
+        let deck = Address::repeat_byte(0xde);
+        let (state_root, response) = fixture(&deck);
+
+        assert_eq!(verify_account_proof(&state_root, &response), Ok(response.storage_hash));
+    }
+
+    #[test]
+    fn test_verify_account_proof_wrong_root() {
+        let (_, response) = fixture(&Address::repeat_byte(0xde));
+
+        assert!(matches!(
+            verify_account_proof(&H256::repeat_byte(1), &response),
+            Err(Error::InvalidProof(_))
+        ));
+    }
+
+    #[test]
+    fn test_verify_proof_absent_key() {
+        let deck = Address::repeat_byte(0xde);
+        let (state_root, response) = fixture(&deck);
+
+        let other = Address::repeat_byte(0xad);
+        assert_eq!(verify_proof(&state_root, other.as_bytes(), &response.account_proof), Ok(None));
+        assert_eq!(verify_proof(&H256(EMPTY_TRIE_ROOT), other.as_bytes(), &[]), Ok(None));
+    }
+
+    #[test]
+    fn test_verify_storage_proof() {
+        let (_, response) = fixture(&Address::repeat_byte(0xde));
+
+        assert_eq!(
+            verify_storage_proof(&response.storage_hash, &[0; 32], &response.storage_proof[0]),
+            Ok(H256::from_low_u64_be(5))
+        );
+    }
+
+    #[test]
+    fn test_verify_storage_proof_wrong_value() {
+        let (_, mut response) = fixture(&Address::repeat_byte(0xde));
+        response.storage_proof[0].value = U256::from(6);
+
+        assert!(matches!(
+            verify_storage_proof(&response.storage_hash, &[0; 32], &response.storage_proof[0]),
+            Err(Error::InvalidProof(_))
+        ));
+    }
+
+    #[test]
+    fn test_verify_storage_proof_tampered_node() {
+        let (_, mut response) = fixture(&Address::repeat_byte(0xde));
+        let tampered = leaf(&nibbles(&[0; 32])[1..], &encode(&vec![6u8]));
+        response.storage_proof[0].proof[1] = tampered.into();
+        response.storage_proof[0].value = U256::from(6);
+
+        assert!(matches!(
+            verify_storage_proof(&response.storage_hash, &[0; 32], &response.storage_proof[0]),
+            Err(Error::InvalidProof(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_read_proven_state() {
+        let deck = Address::repeat_byte(0xde);
+        let block = H256::repeat_byte(0xbb);
+        let (state_root, response) = fixture(&deck);
+
+        let (provider, mock) = Provider::mocked();
+        mock.push(response).unwrap();
+
+        let state = read_proven_state(provider, &deck, &[], &[], &block, &state_root)
+            .await
+            .unwrap();
+
+        assert_eq!(state.block(), &block);
+        assert_eq!(state.id(), &U256::from(5));
+        assert_eq!(state.threshold(), 2);
+
+        let slots = vec![H256::zero(), H256::from_low_u64_be(1)];
+        mock.assert_request("eth_getProof", (deck, slots, BlockId::Hash(block)))
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_read_proven_state_missing_proofs() {
+        let deck = Address::repeat_byte(0xde);
+        let (state_root, response) = fixture(&deck);
+
+        let (provider, mock) = Provider::mocked();
+        mock.push(response).unwrap();
+
+        let result = read_proven_state(
+            provider,
+            &deck,
+            &[Address::repeat_byte(1)],
+            &[[1; 4]],
+            &H256::zero(),
+            &state_root,
+        )
+        .await;
+
+        assert!(matches!(result, Err(Error::InvalidProof(_))));
+    }
+}
@@ -13,11 +13,23 @@ pub async fn read_id<M>(
     provider: M,
     deck: &Address,
 ) -> Result<U256, Error>
+where
+    M: Middleware + 'static,
+{
+    read_id_at(provider, deck, None).await
+}
+
+/// ## Read ID At Block
+pub async fn read_id_at<M>(
+    provider: M,
+    deck: &Address,
+    block: Option<BlockId>,
+) -> Result<U256, Error>
 where
     M: Middleware + 'static,
 {
     provider
-        .get_storage_at(*deck, H256::from_slice(&u256_to_be(&id_slot())), None)
+        .get_storage_at(*deck, H256::from_slice(&u256_to_be(&id_slot())), block)
         .await
         .map(|res| U256::from(res.as_ref()))
         .map_err(|_| Error::Middleware("Failed to read ID".to_string()))
@@ -28,6 +40,18 @@ pub async fn read_threshold<M>(
     provider: M,
     deck: &Address,
 ) -> Result<u8, Error>
+where
+    M: Middleware + 'static,
+{
+    read_threshold_at(provider, deck, None).await
+}
+
+/// ## Read Threshold At Block
+pub async fn read_threshold_at<M>(
+    provider: M,
+    deck: &Address,
+    block: Option<BlockId>,
+) -> Result<u8, Error>
 where
     M: Middleware + 'static,
 {
@@ -35,7 +59,7 @@ where
         .get_storage_at(
             *deck,
             H256::from_slice(&u256_to_be(&threshold_slot())),
-            None,
+            block,
         )
         .await
         .map(|res| res.as_ref()[31])
@@ -48,6 +72,19 @@ pub async fn read_auth<M>(
     deck: &Address,
     account: &Address,
 ) -> Result<bool, Error>
+where
+    M: Middleware + 'static,
+{
+    read_auth_at(provider, deck, account, None).await
+}
+
+/// ## Read Authorization At Block
+pub async fn read_auth_at<M>(
+    provider: M,
+    deck: &Address,
+    account: &Address,
+    block: Option<BlockId>,
+) -> Result<bool, Error>
 where
     M: Middleware + 'static,
 {
@@ -55,7 +92,7 @@ where
         .get_storage_at(
             *deck,
             H256::from_slice(&auth_slot(account)),
-            None,
+            block,
         )
         .await
         .map(|res| res.as_ref()[31] == 1)
@@ -63,11 +100,28 @@ where
 }
 
 /// ## Read Shard
+///
+/// The shard is stored right-aligned in its slot; `None` if no shard is registered.
 pub async fn read_shard<M>(
     provider: M,
     deck: &Address,
     selector: &[u8; 4],
-) -> Result<Address, Error>
+) -> Result<Option<Address>, Error>
+where
+    M: Middleware + 'static,
+{
+    read_shard_at(provider, deck, selector, None).await
+}
+
+/// ## Read Shard At Block
+///
+/// The shard is stored right-aligned in its slot; `None` if no shard is registered.
+pub async fn read_shard_at<M>(
+    provider: M,
+    deck: &Address,
+    selector: &[u8; 4],
+    block: Option<BlockId>,
+) -> Result<Option<Address>, Error>
 where
     M: Middleware + 'static,
 {
@@ -75,9 +129,104 @@ where
         .get_storage_at(
             *deck,
             H256::from_slice(&shard_slot(selector)),
-            None,
+            block,
         )
         .await
-        .map(|res| Address::from_slice(res.as_ref()))
+        .map(|res| Some(Address::from(res)).filter(|shard| !shard.is_zero()))
         .map_err(|_| Error::Middleware("Failed to read shard".to_string()))
 }
+
+/// ## Shard Status Enum
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ShardStatus {
+    /// No shard is registered; the fallback reverts with `0xef160e8e`.
+    NotFound,
+    /// The shard has no code; the fallback's delegatecall succeeds without executing anything.
+    Codeless(Address),
+    /// The shard has code.
+    Deployed(Address),
+}
+
+/// ## Read Shard Status
+pub async fn read_shard_status<M>(
+    provider: M,
+    deck: &Address,
+    selector: &[u8; 4],
+) -> Result<ShardStatus, Error>
+where
+    M: Middleware + Clone + 'static,
+{
+    read_shard_status_at(provider, deck, selector, None).await
+}
+
+/// ## Read Shard Status At Block
+pub async fn read_shard_status_at<M>(
+    provider: M,
+    deck: &Address,
+    selector: &[u8; 4],
+    block: Option<BlockId>,
+) -> Result<ShardStatus, Error>
+where
+    M: Middleware + Clone + 'static,
+{
+    let shard = match read_shard_at(provider.clone(), deck, selector, block).await? {
+        Some(shard) => shard,
+        None => return Ok(ShardStatus::NotFound),
+    };
+
+    let code = provider
+        .get_code(shard, block)
+        .await
+        .map_err(|_| Error::Middleware("Failed to read shard code".to_string()))?;
+
+    match code.is_empty() {
+        true => Ok(ShardStatus::Codeless(shard)),
+        false => Ok(ShardStatus::Deployed(shard)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_read_shard_low_bytes() {
+        let mut word = [0xffu8; 32];
+        word[12..].copy_from_slice(Address::repeat_byte(0xa).as_bytes());
+
+        let (provider, mock) = Provider::mocked();
+        mock.push(H256(word)).unwrap();
+
+        let shard = read_shard(provider, &Address::zero(), &[1; 4]).await.unwrap();
+
+        assert_eq!(shard, Some(Address::repeat_byte(0xa)));
+    }
+
+    #[tokio::test]
+    async fn test_read_shard_unset() {
+        let (provider, mock) = Provider::mocked();
+        mock.push(H256::zero()).unwrap();
+
+        assert_eq!(read_shard(provider, &Address::zero(), &[1; 4]).await, Ok(None));
+    }
+
+    #[tokio::test]
+    async fn test_read_shard_status() {
+        let shard = Address::repeat_byte(0xa);
+
+        let (provider, mock) = Provider::mocked();
+        // responses are popped in reverse order
+        mock.push::<Bytes, _>(Bytes::new()).unwrap();
+        mock.push(H256::from(shard)).unwrap();
+        mock.push::<Bytes, _>(Bytes::from(vec![0x00])).unwrap();
+        mock.push(H256::from(shard)).unwrap();
+        mock.push(H256::zero()).unwrap();
+
+        let deck = Address::zero();
+        let status = |provider| async move { read_shard_status(provider, &deck, &[1; 4]).await };
+
+        assert_eq!(status(provider.clone()).await, Ok(ShardStatus::NotFound));
+        assert_eq!(status(provider.clone()).await, Ok(ShardStatus::Deployed(shard)));
+        assert_eq!(status(provider).await, Ok(ShardStatus::Codeless(shard)));
+    }
+}
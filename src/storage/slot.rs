@@ -36,7 +36,7 @@ pub fn threshold_slot() -> U256 {
 /// 
 /// The authorization storage slot for the account.
 pub fn auth_slot(account: &Address) -> [u8; 32] {
-    keccak256(&encode(&[
+    keccak256(encode(&[
         Token::Address(*account),
         Token::Uint(U256::from(AUTH_INDEX)),
     ]))
@@ -52,7 +52,7 @@ pub fn auth_slot(account: &Address) -> [u8; 32] {
 /// 
 /// The shard storage slot for the selector.
 pub fn shard_slot(selector: &[u8; 4]) -> [u8; 32] {
-    keccak256(&encode(&[
+    keccak256(encode(&[
         Token::FixedBytes(selector.to_vec()),
         Token::Uint(U256::from(SHARD_INDEX)),
     ]))
@@ -0,0 +1,365 @@
+//! # Ether Deck Storage State Module
+//!
+//! Contains a snapshot of the deck's storage, read at a single block.
+use crate::{
+    error::Error,
+    storage::reader::{read_auth_at, read_id_at, read_shard_at, read_threshold_at},
+};
+use ethers::prelude::*;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// ## Deck State
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeckState {
+    block: H256,
+    id: U256,
+    threshold: u8,
+    auths: BTreeMap<Address, bool>,
+    shards: BTreeMap<[u8; 4], Option<Address>>,
+}
+
+impl DeckState {
+    /// ### Create New Deck State
+    ///
+    /// #### Parameters
+    ///
+    /// - `block`: The hash of the block the state was read at.
+    /// - `id`: The syscall id.
+    /// - `threshold`: The threshold.
+    /// - `auths`: The authorization of each read account.
+    /// - `shards`: The shard of each read selector, `None` if not registered.
+    pub fn new(
+        block: &H256,
+        id: &U256,
+        threshold: u8,
+        auths: BTreeMap<Address, bool>,
+        shards: BTreeMap<[u8; 4], Option<Address>>,
+    ) -> Self {
+        Self {
+            block: *block,
+            id: *id,
+            threshold,
+            auths,
+            shards,
+        }
+    }
+
+    /// ### Get the hash of the block the state was read at
+    pub fn block(&self) -> &H256 {
+        &self.block
+    }
+
+    /// ### Get the syscall id
+    pub fn id(&self) -> &U256 {
+        &self.id
+    }
+
+    /// ### Get the threshold
+    pub fn threshold(&self) -> u8 {
+        self.threshold
+    }
+
+    /// ### Get the authorization of each read account
+    pub fn auths(&self) -> &BTreeMap<Address, bool> {
+        &self.auths
+    }
+
+    /// ### Get the shard of each read selector, `None` if not registered
+    pub fn shards(&self) -> &BTreeMap<[u8; 4], Option<Address>> {
+        &self.shards
+    }
+
+    /// ### Diff Deck States
+    ///
+    /// Accounts and selectors read in only one of the states are reported with `None` on the
+    /// other side. A selector read without a registered shard is reported as `Some(None)`.
+    ///
+    /// #### Parameters
+    ///
+    /// - `other`: The later state.
+    ///
+    /// #### Returns
+    ///
+    /// The changes from `self` to `other`.
+    pub fn diff(&self, other: &DeckState) -> Vec<StateChange> {
+        let mut changes = Vec::new();
+
+        if self.id != other.id {
+            changes.push(StateChange::Id {
+                from: self.id,
+                to: other.id,
+            });
+        }
+
+        if self.threshold != other.threshold {
+            changes.push(StateChange::Threshold {
+                from: self.threshold,
+                to: other.threshold,
+            });
+        }
+
+        let accounts: BTreeSet<&Address> = self.auths.keys().chain(other.auths.keys()).collect();
+        for account in accounts {
+            let (from, to) = (self.auths.get(account), other.auths.get(account));
+            if from != to {
+                changes.push(StateChange::Auth {
+                    account: *account,
+                    from: from.copied(),
+                    to: to.copied(),
+                });
+            }
+        }
+
+        let selectors: BTreeSet<&[u8; 4]> =
+            self.shards.keys().chain(other.shards.keys()).collect();
+        for selector in selectors {
+            let (from, to) = (self.shards.get(selector), other.shards.get(selector));
+            if from != to {
+                changes.push(StateChange::Shard {
+                    selector: *selector,
+                    from: from.copied(),
+                    to: to.copied(),
+                });
+            }
+        }
+
+        changes
+    }
+}
+
+/// ## State Change Enum
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StateChange {
+    /// Syscall ID Change
+    Id { from: U256, to: U256 },
+    /// Threshold Change
+    Threshold { from: u8, to: u8 },
+    /// Authorization Change
+    Auth {
+        account: Address,
+        from: Option<bool>,
+        to: Option<bool>,
+    },
+    /// Shard Change
+    ///
+    /// `None` if the selector was not read, `Some(None)` if it had no registered shard.
+    Shard {
+        selector: [u8; 4],
+        from: Option<Option<Address>>,
+        to: Option<Option<Address>>,
+    },
+}
+
+/// ## Read State
+///
+/// The block is resolved to its hash first, so every read is made against the same block even
+/// if `block` is a tag such as `latest`.
+///
+/// ### Parameters
+///
+/// - `provider`: The provider to read the deck with.
+/// - `deck`: The deck's address.
+/// - `accounts`: The accounts to read authorization for.
+/// - `selectors`: The selectors to read shards for.
+/// - `block`: The block to read at.
+///
+/// ### Returns
+///
+/// Either the [DeckState] or an [Error](crate::error::Error).
+pub async fn read_state<M>(
+    provider: M,
+    deck: &Address,
+    accounts: &[Address],
+    selectors: &[[u8; 4]],
+    block: BlockId,
+) -> Result<DeckState, Error>
+where
+    M: Middleware + Clone + 'static,
+{
+    let hash = provider
+        .get_block(block)
+        .await
+        .map_err(|e| Error::Middleware(e.to_string()))?
+        .and_then(|block| block.hash)
+        .ok_or(Error::Middleware("No block".to_string()))?;
+    let pinned = Some(BlockId::Hash(hash));
+
+    let id = read_id_at(provider.clone(), deck, pinned).await?;
+    let threshold = read_threshold_at(provider.clone(), deck, pinned).await?;
+
+    let mut auths = BTreeMap::new();
+    for account in accounts {
+        auths.insert(*account, read_auth_at(provider.clone(), deck, account, pinned).await?);
+    }
+
+    let mut shards = BTreeMap::new();
+    for selector in selectors {
+        shards.insert(*selector, read_shard_at(provider.clone(), deck, selector, pinned).await?);
+    }
+
+    Ok(DeckState::new(&hash, &id, threshold, auths, shards))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(id: u64, threshold: u8, auths: &[(u8, bool)], shards: &[(u8, u8)]) -> DeckState {
+        DeckState::new(
+            &H256::zero(),
+            &U256::from(id),
+            threshold,
+            auths
+                .iter()
+                .map(|(account, auth)| (Address::repeat_byte(*account), *auth))
+                .collect(),
+            shards
+                .iter()
+                .map(|(selector, shard)| ([*selector; 4], Some(Address::repeat_byte(*shard))))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_diff_unchanged() {
+        let before = state(1, 2, &[(1, true)], &[(1, 0xa)]);
+
+        assert_eq!(before.diff(&before.clone()), vec![]);
+    }
+
+    #[test]
+    fn test_diff_changes() {
+        let before = state(1, 1, &[(1, true), (2, false)], &[(1, 0xa)]);
+        let after = state(2, 2, &[(1, true), (2, true)], &[(1, 0xb)]);
+
+        assert_eq!(
+            before.diff(&after),
+            vec![
+                StateChange::Id {
+                    from: U256::from(1),
+                    to: U256::from(2)
+                },
+                StateChange::Threshold { from: 1, to: 2 },
+                StateChange::Auth {
+                    account: Address::repeat_byte(2),
+                    from: Some(false),
+                    to: Some(true),
+                },
+                StateChange::Shard {
+                    selector: [1; 4],
+                    from: Some(Some(Address::repeat_byte(0xa))),
+                    to: Some(Some(Address::repeat_byte(0xb))),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diff_unread_keys() {
+        let before = state(1, 1, &[(1, true)], &[]);
+        let after = state(1, 1, &[], &[(1, 0xa)]);
+
+        assert_eq!(
+            before.diff(&after),
+            vec![
+                StateChange::Auth {
+                    account: Address::repeat_byte(1),
+                    from: Some(true),
+                    to: None,
+                },
+                StateChange::Shard {
+                    selector: [1; 4],
+                    from: None,
+                    to: Some(Some(Address::repeat_byte(0xa))),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diff_unset_shards() {
+        let mut before = state(1, 1, &[], &[(1, 0xa)]);
+        before.shards.insert([2; 4], None);
+        let mut after = state(1, 1, &[], &[(2, 0xb)]);
+        after.shards.insert([1; 4], None);
+
+        assert_eq!(
+            before.diff(&after),
+            vec![
+                StateChange::Shard {
+                    selector: [1; 4],
+                    from: Some(Some(Address::repeat_byte(0xa))),
+                    to: Some(None),
+                },
+                StateChange::Shard {
+                    selector: [2; 4],
+                    from: Some(None),
+                    to: Some(Some(Address::repeat_byte(0xb))),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diff_unread_and_unset_shards() {
+        let mut before = state(1, 1, &[], &[(1, 0xa)]);
+        before.shards.insert([2; 4], None);
+        let after = state(1, 1, &[], &[]);
+
+        assert_eq!(
+            before.diff(&after),
+            vec![
+                StateChange::Shard {
+                    selector: [1; 4],
+                    from: Some(Some(Address::repeat_byte(0xa))),
+                    to: None,
+                },
+                StateChange::Shard {
+                    selector: [2; 4],
+                    from: Some(None),
+                    to: None,
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_read_state_pins_block() {
+        let hash = H256::repeat_byte(0xbb);
+        let block = Block::<TxHash> {
+            hash: Some(hash),
+            ..Default::default()
+        };
+        let deck = Address::repeat_byte(0xde);
+        let account = Address::repeat_byte(1);
+
+        let (provider, mock) = Provider::mocked();
+        // responses are popped in reverse order
+        mock.push(H256::from(Address::repeat_byte(0xa))).unwrap();
+        mock.push(H256::from_low_u64_be(1)).unwrap();
+        mock.push(H256::from_low_u64_be(2)).unwrap();
+        mock.push(H256::from_low_u64_be(5)).unwrap();
+        mock.push(block).unwrap();
+
+        let state = read_state(
+            provider,
+            &deck,
+            &[account],
+            &[[1; 4]],
+            BlockNumber::Latest.into(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(state.block(), &hash);
+        assert_eq!(state.id(), &U256::from(5));
+        assert_eq!(state.threshold(), 2);
+        assert!(state.auths()[&account]);
+        assert_eq!(state.shards()[&[1; 4]], Some(Address::repeat_byte(0xa)));
+
+        let pinned = BlockId::Hash(hash);
+        mock.assert_request("eth_getBlockByNumber", ("latest", false)).unwrap();
+        mock.assert_request("eth_getStorageAt", (deck, U256::zero(), pinned))
+            .unwrap();
+    }
+}
@@ -0,0 +1,579 @@
+//! # Ether Deck Trace Module
+//!
+//! Contains an annotator for opcode-level traces of calls to the deck.
+//!
+//! Steps in the deck's frame are labelled with the [Region] of the deck's runtime code their
+//! program counter falls in. Program counters are offsets into the runtime code of
+//! [ETHER_DECK_BYTECODE](crate::deploy::ETHER_DECK_BYTECODE), which the constructor copies from
+//! offset `0x1f`. Steps from a local [Simulator](crate::simulate::Simulator::trace) or from
+//! `debug_traceTransaction` are annotated alike.
+use crate::error::Error;
+use ethers::prelude::*;
+use std::fmt::Write;
+
+/// Recovered signer on top of the stack, after the `ecrecover` result is loaded.
+const SIGNER_PC: u64 = 0x143;
+/// Auth slot value on top of the stack, over the ordering check, before both are accumulated.
+const AUTH_PC: u64 = 0x155;
+/// Fallback revert when no shard is set.
+const SHARD_NOT_FOUND_PC: u64 = 0x89;
+/// Fallback revert bubbling the shard's revert.
+const SHARD_REVERT_PC: u64 = 0x5a;
+/// Deadline check revert.
+const DEADLINE_PC: u64 = 0xbf;
+/// Signature check revert.
+const UNAUTHORIZED_PC: u64 = 0x18a;
+/// Syscall revert bubbling the inner call's revert.
+const INNER_REVERT_PC: u64 = 0x1e7;
+/// Caller checks of `setAuth`, `setThreshold`, and `setShard`.
+const CALLER_CHECK_PCS: [u64; 3] = [0x21d, 0x292, 0x2f5];
+
+/// Regions of the runtime code, by inclusive program counter range.
+const REGIONS: [(u64, u64, Region); 11] = [
+    (0x000, 0x025, Region::Dispatch),
+    (0x026, 0x089, Region::Fallback),
+    (0x08a, 0x0bf, Region::DeadlineCheck),
+    (0x0c0, 0x11c, Region::Digest),
+    (0x11d, 0x15d, Region::SignatureLoop),
+    (0x15e, 0x18a, Region::AuthCheck),
+    (0x18b, 0x1be, Region::SyscallLog),
+    (0x1bf, 0x1ec, Region::InnerCall),
+    (0x1ed, 0x261, Region::SetAuth),
+    (0x262, 0x2c4, Region::SetThreshold),
+    (0x2c5, 0x33c, Region::SetShard),
+];
+
+/// ## Code Region Enum
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Region {
+    /// Selector dispatch.
+    Dispatch,
+    /// Fallback delegatecall to the selector's shard.
+    Fallback,
+    /// Syscall deadline check.
+    DeadlineCheck,
+    /// Syscall digest.
+    Digest,
+    /// Signature recovery, ordering, and auth slot reads.
+    SignatureLoop,
+    /// Signature check result.
+    AuthCheck,
+    /// `Syscall` log and id increment.
+    SyscallLog,
+    /// Syscall inner call.
+    InnerCall,
+    /// `setAuth`.
+    SetAuth,
+    /// `setThreshold`.
+    SetThreshold,
+    /// `setShard`.
+    SetShard,
+}
+
+impl Region {
+    /// ### Get the region of a runtime code program counter
+    pub fn of(pc: u64) -> Option<Region> {
+        REGIONS
+            .iter()
+            .find(|(start, end, _)| (*start..=*end).contains(&pc))
+            .map(|(_, _, region)| *region)
+    }
+
+    /// ### Get the region's label
+    pub fn label(&self) -> &'static str {
+        match self {
+            Region::Dispatch => "dispatch",
+            Region::Fallback => "fallback delegatecall",
+            Region::DeadlineCheck => "deadline check",
+            Region::Digest => "digest",
+            Region::SignatureLoop => "signature loop",
+            Region::AuthCheck => "auth check",
+            Region::SyscallLog => "syscall log",
+            Region::InnerCall => "inner call",
+            Region::SetAuth => "setAuth",
+            Region::SetThreshold => "setThreshold",
+            Region::SetShard => "setShard",
+        }
+    }
+}
+
+/// ## Trace Step
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Step {
+    pc: u64,
+    op: String,
+    depth: u64,
+    gas: u64,
+    stack: Vec<U256>,
+}
+
+impl Step {
+    /// ### Create New Step
+    ///
+    /// #### Parameters
+    ///
+    /// - `pc`: The program counter.
+    /// - `op`: The opcode's name.
+    /// - `depth`: The call depth, one for the transaction's frame.
+    /// - `gas`: The remaining gas.
+    /// - `stack`: The stack before the step, top last.
+    pub fn new(pc: u64, op: &str, depth: u64, gas: u64, stack: Vec<U256>) -> Self {
+        Self {
+            pc,
+            op: op.to_string(),
+            depth,
+            gas,
+            stack,
+        }
+    }
+
+    /// ### Get the program counter
+    pub fn pc(&self) -> u64 {
+        self.pc
+    }
+
+    /// ### Get the opcode's name
+    pub fn op(&self) -> &str {
+        &self.op
+    }
+
+    /// ### Get the call depth
+    pub fn depth(&self) -> u64 {
+        self.depth
+    }
+
+    /// ### Get the remaining gas
+    pub fn gas(&self) -> u64 {
+        self.gas
+    }
+
+    /// ### Get the stack, top last
+    pub fn stack(&self) -> &[U256] {
+        &self.stack
+    }
+
+    /// Returns the stack item at a depth from the top.
+    fn peek(&self, depth: usize) -> Option<&U256> {
+        self.stack.iter().rev().nth(depth)
+    }
+}
+
+impl From<&StructLog> for Step {
+    fn from(log: &StructLog) -> Self {
+        Self::new(log.pc, &log.op, log.depth, log.gas, log.stack.clone().unwrap_or_default())
+    }
+}
+
+/// ## Signature Check
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignatureCheck {
+    signer: Address,
+    ordered: bool,
+    authorized: bool,
+}
+
+impl SignatureCheck {
+    /// ### Get the signer the deck reads back from `ecrecover`
+    ///
+    /// A failed recovery returns no data, so the deck reads the signer left in memory by the
+    /// previous signature, or zero for the first. The stale signer is not greater than itself, so
+    /// the check is not ordered.
+    pub fn signer(&self) -> &Address {
+        &self.signer
+    }
+
+    /// ### Get whether the signer is greater than the previous signer
+    pub fn is_ordered(&self) -> bool {
+        self.ordered
+    }
+
+    /// ### Get whether the signer is authorized
+    pub fn is_authorized(&self) -> bool {
+        self.authorized
+    }
+
+    /// ### Get whether the deck accepts the signature
+    pub fn is_valid(&self) -> bool {
+        self.ordered && self.authorized
+    }
+}
+
+/// ## Trace Failure Enum
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Failure {
+    /// The fallback found no shard for the selector.
+    ShardNotFound,
+    /// The shard reverted.
+    ShardReverted,
+    /// The syscall deadline has passed.
+    DeadlineExpired,
+    /// A signature was rejected, by index into the trace's signature checks.
+    SignatureRejected(usize),
+    /// A gated method was called by an account other than the deck.
+    CallerNotDeck,
+    /// The syscall's inner call reverted.
+    InnerCallReverted,
+    /// Execution halted, such as out of gas, in the region if known.
+    Halted(Option<Region>),
+}
+
+/// ## Annotated Trace
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trace {
+    steps: Vec<Step>,
+    signatures: Vec<SignatureCheck>,
+    failure: Option<Failure>,
+}
+
+impl Trace {
+    /// ### Get the steps
+    pub fn steps(&self) -> &[Step] {
+        &self.steps
+    }
+
+    /// ### Get the signature checks, in signature order
+    pub fn signatures(&self) -> &[SignatureCheck] {
+        &self.signatures
+    }
+
+    /// ### Get the failure, `None` if the deck's frame succeeded
+    pub fn failure(&self) -> Option<&Failure> {
+        self.failure.as_ref()
+    }
+
+    /// ### Get the first rejected signature's check
+    pub fn rejected_signature(&self) -> Option<&SignatureCheck> {
+        match self.failure {
+            Some(Failure::SignatureRejected(index)) => self.signatures.get(index),
+            _ => None,
+        }
+    }
+
+    /// ### Render the trace
+    ///
+    /// Prints one line per step, the deck's steps under a header for each region they enter, then
+    /// the signature checks and the failure.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let mut region = None;
+
+        for step in &self.steps {
+            if step.depth == 1 && Region::of(step.pc) != region {
+                region = Region::of(step.pc);
+                let label = region.map(|region| region.label()).unwrap_or("unknown");
+                let _ = writeln!(out, "-- {} --", label);
+            }
+            let indent = "  ".repeat(step.depth.saturating_sub(1) as usize);
+            let _ = writeln!(out, "{}{:04x} {:<14} gas {}", indent, step.pc, step.op, step.gas);
+        }
+
+        for (index, check) in self.signatures.iter().enumerate() {
+            let _ = writeln!(
+                out,
+                "signature {}: {:?} ordered {} authorized {}",
+                index, check.signer, check.ordered, check.authorized
+            );
+        }
+
+        let _ = match (&self.failure, self.rejected_signature()) {
+            (None, _) => writeln!(out, "success"),
+            (Some(_), Some(check)) => writeln!(out, "failed: rejected signer {:?}", check.signer),
+            (Some(failure), None) => writeln!(out, "failed: {:?}", failure),
+        };
+        out
+    }
+}
+
+/// ## Annotate Steps
+///
+/// ### Parameters
+///
+/// - `steps`: The steps of a call to the deck, the deck's frame at depth one.
+///
+/// ### Returns
+///
+/// The annotated [Trace].
+pub fn annotate(steps: Vec<Step>) -> Trace {
+    let mut signatures = Vec::new();
+    let mut signer = None;
+
+    for step in steps.iter().filter(|step| step.depth == 1) {
+        match step.pc {
+            SIGNER_PC => signer = step.peek(0).map(address),
+            AUTH_PC => {
+                if let (Some(signer), Some(authorized), Some(ordered)) =
+                    (signer.take(), step.peek(0), step.peek(1))
+                {
+                    signatures.push(SignatureCheck {
+                        signer,
+                        ordered: !ordered.is_zero(),
+                        authorized: !authorized.is_zero(),
+                    });
+                }
+            }
+            _ => (),
+        }
+    }
+
+    let failure = steps
+        .iter()
+        .rev()
+        .find(|step| step.depth == 1)
+        .and_then(|last| failure(last, &signatures));
+
+    Trace {
+        steps,
+        signatures,
+        failure,
+    }
+}
+
+/// ## Trace Transaction
+///
+/// Replays a transaction to the deck with `debug_traceTransaction` and the struct logger.
+///
+/// ### Parameters
+///
+/// - `provider`: The provider to trace with.
+/// - `hash`: The transaction hash.
+///
+/// ### Returns
+///
+/// Either the annotated [Trace] or an [Error](crate::error::Error).
+pub async fn trace_transaction<M>(provider: M, hash: &H256) -> Result<Trace, Error>
+where
+    M: Middleware + 'static,
+{
+    let options = GethDebugTracingOptions {
+        disable_storage: Some(true),
+        disable_stack: Some(false),
+        enable_memory: Some(false),
+        enable_return_data: Some(false),
+        ..Default::default()
+    };
+
+    match provider
+        .debug_trace_transaction(*hash, options)
+        .await
+        .map_err(|e| Error::Middleware(e.to_string()))?
+    {
+        GethTrace::Known(GethTraceFrame::Default(frame)) => {
+            Ok(annotate(frame.struct_logs.iter().map(Step::from).collect()))
+        }
+        _ => Err(Error::Middleware("Expected a struct log trace".to_string())),
+    }
+}
+
+/// Classifies the deck frame's last step.
+fn failure(last: &Step, signatures: &[SignatureCheck]) -> Option<Failure> {
+    let failure = match (last.op.as_str(), last.pc) {
+        ("STOP" | "RETURN", _) => return None,
+        ("REVERT", SHARD_NOT_FOUND_PC) => Failure::ShardNotFound,
+        ("REVERT", SHARD_REVERT_PC) => Failure::ShardReverted,
+        ("REVERT", DEADLINE_PC) => Failure::DeadlineExpired,
+        ("REVERT", UNAUTHORIZED_PC) => Failure::SignatureRejected(
+            signatures
+                .iter()
+                .position(|check| !check.is_valid())
+                .unwrap_or_default(),
+        ),
+        ("REVERT", INNER_REVERT_PC) => Failure::InnerCallReverted,
+        ("REVERT", pc) if CALLER_CHECK_PCS.contains(&pc) => Failure::CallerNotDeck,
+        (_, pc) => Failure::Halted(Region::of(pc)),
+    };
+    Some(failure)
+}
+
+/// Returns a word's low 20 bytes.
+fn address(word: &U256) -> Address {
+    let mut bytes = [0u8; 32];
+    word.to_big_endian(&mut bytes);
+    Address::from_slice(&bytes[12..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        coder::{
+            encoder::{encode, encode_set_threshold, encode_syscall},
+            structures::{DeckSignature, Encoding, Syscall},
+        },
+        sign::sign_syscall,
+        simulate::{
+            fixture::{deck_with_owners, RELAYER},
+            Simulator,
+        },
+    };
+    use ethers::signers::LocalWallet;
+    use serde_json::json;
+
+    fn wallet(key: u8) -> LocalWallet {
+        LocalWallet::from_bytes(&[key; 32]).unwrap()
+    }
+
+    /// Deploys a deck owned by the first two wallets with a threshold of the signer count, and
+    /// traces a syscall signed by the signers, optionally with the signatures reversed.
+    async fn trace_syscall(signers: &[LocalWallet], reverse: bool, deadline: u64) -> Trace {
+        let mut sim = Simulator::new(1);
+        let (deployer, owner) = (wallet(1).address(), wallet(2).address());
+        let threshold = signers.len() as u8;
+        let deck = deck_with_owners(&mut sim, &deployer, &[owner], threshold, &U256::zero());
+        sim.set_timestamp(10);
+
+        let unsigned = Syscall::new(
+            &U256::zero(),
+            &Address::repeat_byte(0x7a),
+            &U256::zero(),
+            &U64::from(deadline),
+            &[],
+            &[],
+        );
+        let mut signed = sign_syscall(&unsigned, &U256::one(), signers).await.unwrap();
+        if reverse {
+            let mut signatures = signed.signatures().to_vec();
+            signatures.reverse();
+            signed = Syscall::new(
+                signed.id(),
+                signed.target(),
+                signed.value(),
+                signed.deadline(),
+                signed.payload(),
+                &signatures,
+            );
+        }
+
+        let calldata = encode(&Encoding::Syscall(signed)).unwrap();
+        let (_, trace) = sim.trace(&RELAYER, &deck, &U256::zero(), &calldata).unwrap();
+        trace
+    }
+
+    #[test]
+    fn test_regions() {
+        assert_eq!(Region::of(0x00), Some(Region::Dispatch));
+        assert_eq!(Region::of(0x4c), Some(Region::Fallback));
+        assert_eq!(Region::of(SIGNER_PC), Some(Region::SignatureLoop));
+        assert_eq!(Region::of(UNAUTHORIZED_PC), Some(Region::AuthCheck));
+        assert_eq!(Region::of(0x1d9), Some(Region::InnerCall));
+        assert_eq!(Region::of(0x33c), Some(Region::SetShard));
+        assert_eq!(Region::of(0x33d), None);
+    }
+
+    #[tokio::test]
+    async fn test_trace_success() {
+        let trace = trace_syscall(&[wallet(1)], false, 100).await;
+
+        assert_eq!(trace.failure(), None);
+        assert_eq!(trace.signatures().len(), 1);
+        assert_eq!(trace.signatures()[0].signer(), &wallet(1).address());
+        assert!(trace.signatures()[0].is_valid());
+
+        let rendered = trace.render();
+        for label in ["dispatch", "deadline check", "signature loop", "inner call"] {
+            assert!(rendered.contains(&format!("-- {} --", label)), "{}", label);
+        }
+        assert!(rendered.ends_with("success\n"));
+    }
+
+    #[tokio::test]
+    async fn test_trace_unauthorized_signer() {
+        let trace = trace_syscall(&[wallet(3)], false, 100).await;
+
+        assert_eq!(trace.failure(), Some(&Failure::SignatureRejected(0)));
+        let rejected = trace.rejected_signature().unwrap();
+        assert_eq!(rejected.signer(), &wallet(3).address());
+        assert!(rejected.is_ordered());
+        assert!(!rejected.is_authorized());
+        assert!(trace
+            .render()
+            .contains(&format!("failed: rejected signer {:?}", wallet(3).address())));
+    }
+
+    #[tokio::test]
+    async fn test_trace_unordered_signers() {
+        let signers = [wallet(1), wallet(2)];
+        assert_eq!(trace_syscall(&signers, false, 100).await.failure(), None);
+
+        let trace = trace_syscall(&signers, true, 100).await;
+        let (first, second) = (&trace.signatures()[0], &trace.signatures()[1]);
+
+        assert!(first.is_valid());
+        assert!(!second.is_ordered());
+        assert!(second.is_authorized());
+        assert_eq!(trace.failure(), Some(&Failure::SignatureRejected(1)));
+        assert_eq!(trace.rejected_signature(), Some(second));
+    }
+
+    #[tokio::test]
+    async fn test_trace_failed_recovery() {
+        let mut sim = Simulator::new(1);
+        let deck = deck_with_owners(&mut sim, &wallet(1).address(), &[], 2, &U256::zero());
+
+        let unsigned =
+            Syscall::new(&U256::zero(), &deck, &U256::zero(), &U64::from(100), &[], &[]);
+        let signed = sign_syscall(&unsigned, &U256::one(), &[wallet(1)]).await.unwrap();
+        let unrecoverable = DeckSignature::new(27, &U256::zero(), &U256::zero());
+        let signatures = [signed.signatures()[0], unrecoverable];
+        let calldata = encode_syscall(
+            signed.id(),
+            signed.target(),
+            signed.value(),
+            signed.deadline(),
+            signed.payload(),
+            &signatures,
+        )
+        .unwrap();
+        let (_, trace) = sim.trace(&RELAYER, &deck, &U256::zero(), &calldata).unwrap();
+
+        // the second check reads the first signer back from memory
+        let (first, second) = (&trace.signatures()[0], &trace.signatures()[1]);
+        assert!(first.is_valid());
+        assert_eq!(second.signer(), first.signer());
+        assert!(!second.is_ordered());
+        assert_eq!(trace.failure(), Some(&Failure::SignatureRejected(1)));
+    }
+
+    #[tokio::test]
+    async fn test_trace_deadline_expired() {
+        let trace = trace_syscall(&[wallet(1)], false, 10).await;
+
+        assert_eq!(trace.failure(), Some(&Failure::DeadlineExpired));
+        assert!(trace.signatures().is_empty());
+    }
+
+    #[test]
+    fn test_trace_fallback_and_gated_calls() {
+        let mut sim = Simulator::new(1);
+        let deck = sim.deploy(&Address::repeat_byte(9)).unwrap();
+        let caller = Address::repeat_byte(1);
+
+        let (_, trace) = sim.trace(&caller, &deck, &U256::zero(), &[1; 4]).unwrap();
+        assert_eq!(trace.failure(), Some(&Failure::ShardNotFound));
+        assert!(trace.render().contains("-- fallback delegatecall --"));
+
+        let calldata = encode_set_threshold(2);
+        let (_, trace) = sim.trace(&caller, &deck, &U256::zero(), &calldata).unwrap();
+        assert_eq!(trace.failure(), Some(&Failure::CallerNotDeck));
+    }
+
+    #[tokio::test]
+    async fn test_trace_transaction() {
+        let (provider, mock) = Provider::mocked();
+        mock.push(json!({
+            "failed": true,
+            "gas": 21100,
+            "returnValue": "0xc6f221aa",
+            "structLogs": [
+                { "pc": 0x8a, "op": "JUMPDEST", "gas": 100, "gasCost": 1, "depth": 1 },
+                { "pc": 0xbf, "op": "REVERT", "gas": 50, "gasCost": 0, "depth": 1 }
+            ]
+        }))
+        .unwrap();
+
+        let trace = trace_transaction(provider, &H256::zero()).await.unwrap();
+
+        assert_eq!(trace.steps().len(), 2);
+        assert_eq!(trace.failure(), Some(&Failure::DeadlineExpired));
+        assert!(trace.render().contains("-- deadline check --"));
+    }
+}
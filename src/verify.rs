@@ -0,0 +1,417 @@
+//! # Ether Deck Verification Module
+//!
+//! Contains functions for verifying a syscall against the deck's storage before submitting it.
+//!
+//! The deck reads `threshold` signatures (at least one), and reverts unless each recovered signer
+//! is authorized and strictly greater than the previous signer.
+//!
+//! Every read is made against the latest block, pinned by hash. The syscall can be included no
+//! earlier than the next block, whose timestamp is greater than the latest block's, so the
+//! deadline is checked against the latest timestamp plus one.
+use crate::{
+    coder::structures::Syscall,
+    digest::syscall_digest,
+    error::Error,
+    sign::recover_signer,
+    storage::reader::{read_auth_at, read_id_at, read_threshold_at},
+};
+use ethers::prelude::*;
+
+/// ## Signer Report
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignerReport {
+    signer: Option<Address>,
+    authorized: bool,
+    ordered: bool,
+}
+
+impl SignerReport {
+    /// ### Create New Signer Report
+    ///
+    /// #### Parameters
+    ///
+    /// - `signer`: The recovered signer, if any.
+    /// - `authorized`: Whether the signer is authorized.
+    /// - `ordered`: Whether the signer is greater than the previous signer.
+    pub fn new(signer: Option<Address>, authorized: bool, ordered: bool) -> Self {
+        Self {
+            signer,
+            authorized,
+            ordered,
+        }
+    }
+
+    /// ### Get the recovered signer
+    pub fn signer(&self) -> Option<&Address> {
+        self.signer.as_ref()
+    }
+
+    /// ### Get whether the signer is authorized
+    pub fn is_authorized(&self) -> bool {
+        self.authorized
+    }
+
+    /// ### Get whether the signer is greater than the previous signer
+    pub fn is_ordered(&self) -> bool {
+        self.ordered
+    }
+
+    /// ### Get whether the deck accepts the signature
+    pub fn is_valid(&self) -> bool {
+        self.signer.is_some() && self.authorized && self.ordered
+    }
+}
+
+/// ## Syscall Report
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyscallReport {
+    signers: Vec<SignerReport>,
+    threshold: u8,
+    deadline_expired: bool,
+    id_matches: bool,
+}
+
+impl SyscallReport {
+    /// ### Create New Syscall Report
+    ///
+    /// #### Parameters
+    ///
+    /// - `signers`: The signer reports, in signature order.
+    /// - `threshold`: The deck's threshold.
+    /// - `deadline_expired`: Whether the syscall's deadline has passed.
+    /// - `id_matches`: Whether the syscall's id matches the deck's id.
+    pub fn new(
+        signers: Vec<SignerReport>,
+        threshold: u8,
+        deadline_expired: bool,
+        id_matches: bool,
+    ) -> Self {
+        Self {
+            signers,
+            threshold,
+            deadline_expired,
+            id_matches,
+        }
+    }
+
+    /// ### Get the signer reports
+    pub fn signers(&self) -> &[SignerReport] {
+        &self.signers
+    }
+
+    /// ### Get the valid signers
+    pub fn valid_signers(&self) -> Vec<Address> {
+        self.signers
+            .iter()
+            .filter(|report| report.is_valid())
+            .filter_map(|report| report.signer().copied())
+            .collect()
+    }
+
+    /// ### Get the unauthorized signers
+    pub fn unauthorized_signers(&self) -> Vec<Address> {
+        self.signers
+            .iter()
+            .filter(|report| !report.is_authorized())
+            .filter_map(|report| report.signer().copied())
+            .collect()
+    }
+
+    /// ### Get the deck's threshold
+    pub fn threshold(&self) -> u8 {
+        self.threshold
+    }
+
+    /// ### Get the number of signatures the deck reads
+    ///
+    /// The deck's signature loop runs at least once, so a zero threshold still reads a signature.
+    pub fn required_signatures(&self) -> usize {
+        self.threshold.max(1) as usize
+    }
+
+    /// ### Get whether the deadline has passed
+    pub fn is_deadline_expired(&self) -> bool {
+        self.deadline_expired
+    }
+
+    /// ### Get whether the syscall's id matches the deck's id
+    ///
+    /// The deck does not compare ids itself, though a mismatch means the signatures were gathered
+    /// for a different syscall.
+    pub fn is_id_match(&self) -> bool {
+        self.id_matches
+    }
+
+    /// ### Get whether the signatures the deck reads are all valid
+    pub fn is_threshold_met(&self) -> bool {
+        let required = self.required_signatures();
+        self.signers.len() >= required
+            && self.signers[..required]
+                .iter()
+                .all(SignerReport::is_valid)
+    }
+
+    /// ### Get whether the syscall is expected to pass the deck's checks
+    pub fn is_valid(&self) -> bool {
+        self.is_threshold_met() && !self.deadline_expired && self.id_matches
+    }
+}
+
+/// ## Verify Syscall
+///
+/// ### Parameters
+///
+/// - `provider`: The provider to read the deck with.
+/// - `deck`: The deck's address.
+/// - `syscall`: The signed syscall.
+///
+/// ### Returns
+///
+/// Either a [SyscallReport] against the latest block or an [Error](crate::error::Error).
+///
+/// A signature the deck's `ecrecover` cannot recover, including one with a recovery byte other
+/// than 27 or 28, is reported without a signer. As on-chain, it does not replace the previous
+/// signer the next signature is ordered against.
+pub async fn verify_syscall<M>(
+    provider: M,
+    deck: &Address,
+    syscall: &Syscall,
+) -> Result<SyscallReport, Error>
+where
+    M: Middleware + Clone + 'static,
+{
+    let chain_id = provider
+        .get_chainid()
+        .await
+        .map_err(|e| Error::Middleware(e.to_string()))?;
+    let latest = provider
+        .get_block(BlockNumber::Latest)
+        .await
+        .map_err(|e| Error::Middleware(e.to_string()))?
+        .ok_or(Error::Middleware("No latest block".to_string()))?;
+    let block = Some(BlockId::Hash(
+        latest.hash.ok_or(Error::Middleware("No block hash".to_string()))?,
+    ));
+    let id = read_id_at(provider.clone(), deck, block).await?;
+    let threshold = read_threshold_at(provider.clone(), deck, block).await?;
+
+    let digest = syscall_digest(syscall, &chain_id)?;
+    let mut previous = Address::zero();
+    let mut signers = Vec::with_capacity(syscall.signatures().len());
+
+    for signature in syscall.signatures() {
+        let recovered = match signature.v() {
+            27 | 28 => recover_signer(signature, &digest).ok(),
+            _ => None,
+        };
+        let report = match recovered {
+            Some(signer) => {
+                let authorized = read_auth_at(provider.clone(), deck, &signer, block).await?;
+                let ordered = signer > previous;
+                previous = signer;
+                SignerReport::new(Some(signer), authorized, ordered)
+            }
+            None => SignerReport::new(None, false, false),
+        };
+        signers.push(report);
+    }
+
+    Ok(SyscallReport::new(
+        signers,
+        threshold,
+        latest.timestamp + 1 >= U256::from(syscall.deadline().as_u64()),
+        &id == syscall.id(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{coder::structures::DeckSignature, sign::sign_syscall};
+
+    fn report(signer: u8, authorized: bool, ordered: bool) -> SignerReport {
+        SignerReport::new(Some(Address::repeat_byte(signer)), authorized, ordered)
+    }
+
+    fn word(value: u64) -> H256 {
+        H256::from_low_u64_be(value)
+    }
+
+    fn latest(timestamp: u64) -> Block<TxHash> {
+        Block {
+            hash: Some(H256::repeat_byte(0xb1)),
+            timestamp: U256::from(timestamp),
+            ..Default::default()
+        }
+    }
+
+    async fn verify_one(signature: DeckSignature, deadline: u64) -> SyscallReport {
+        let syscall = Syscall::new(
+            &U256::from(3),
+            &Address::repeat_byte(0x42),
+            &U256::zero(),
+            &U64::from(deadline),
+            &[],
+            &[signature],
+        );
+        let (provider, mock) = Provider::mocked();
+        // authorized, threshold, id, block, chain id
+        mock.push(word(1)).unwrap();
+        mock.push(word(1)).unwrap();
+        mock.push(word(3)).unwrap();
+        mock.push(latest(1_000)).unwrap();
+        mock.push(U256::one()).unwrap();
+
+        verify_syscall(provider, &Address::repeat_byte(0xde), &syscall).await.unwrap()
+    }
+
+    async fn signature(deadline: u64) -> DeckSignature {
+        let wallet: LocalWallet = "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80"
+            .parse()
+            .unwrap();
+        let unsigned = Syscall::new(
+            &U256::from(3),
+            &Address::repeat_byte(0x42),
+            &U256::zero(),
+            &U64::from(deadline),
+            &[],
+            &[],
+        );
+        sign_syscall(&unsigned, &U256::one(), &[wallet]).await.unwrap().signatures()[0]
+    }
+
+    #[test]
+    fn test_report_valid() {
+        let signers = vec![report(1, true, true), report(2, true, true)];
+        let report = SyscallReport::new(signers, 2, false, true);
+
+        assert!(report.is_valid());
+        assert_eq!(report.valid_signers().len(), 2);
+    }
+
+    #[test]
+    fn test_report_below_threshold() {
+        let report = SyscallReport::new(vec![report(1, true, true)], 2, false, true);
+
+        assert!(!report.is_threshold_met());
+        assert!(!report.is_valid());
+    }
+
+    #[test]
+    fn test_report_unauthorized() {
+        let signers = vec![report(1, true, true), report(2, false, true)];
+        let report = SyscallReport::new(signers, 2, false, true);
+
+        assert!(!report.is_valid());
+        assert_eq!(report.unauthorized_signers(), vec![Address::repeat_byte(2)]);
+    }
+
+    #[test]
+    fn test_report_ignores_extra_signatures() {
+        let signers = vec![report(1, true, true), report(0, false, false)];
+        let report = SyscallReport::new(signers, 1, false, true);
+
+        assert!(report.is_valid());
+    }
+
+    #[test]
+    fn test_report_zero_threshold_reads_one() {
+        let report = SyscallReport::new(vec![], 0, false, true);
+
+        assert_eq!(report.required_signatures(), 1);
+        assert!(!report.is_valid());
+    }
+
+    #[test]
+    fn test_report_deadline_and_id() {
+        let signers = vec![report(1, true, true)];
+
+        assert!(!SyscallReport::new(signers.clone(), 1, true, true).is_valid());
+        assert!(!SyscallReport::new(signers, 1, false, false).is_valid());
+    }
+
+    #[tokio::test]
+    async fn test_verify_syscall() {
+        let wallets: Vec<LocalWallet> = [
+            "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+            "59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d",
+        ]
+        .iter()
+        .map(|key| key.parse().unwrap())
+        .collect();
+        let unsigned = Syscall::new(
+            &U256::from(3),
+            &Address::repeat_byte(0x42),
+            &U256::zero(),
+            &U64::from(2_000),
+            &[],
+            &[],
+        );
+        let signed = sign_syscall(&unsigned, &U256::one(), &wallets).await.unwrap();
+        let mut signatures = signed.signatures().to_vec();
+        signatures.push(DeckSignature::new(0, &U256::zero(), &U256::zero()));
+        let syscall = Syscall::new(
+            signed.id(),
+            signed.target(),
+            signed.value(),
+            signed.deadline(),
+            signed.payload(),
+            &signatures,
+        );
+
+        let (provider, mock) = Provider::mocked();
+        // responses are popped in reverse order
+        mock.push(word(0)).unwrap();
+        mock.push(word(1)).unwrap();
+        mock.push(word(2)).unwrap();
+        mock.push(word(3)).unwrap();
+        mock.push(latest(1_000)).unwrap();
+        mock.push(U256::one()).unwrap();
+
+        let report = verify_syscall(provider, &Address::repeat_byte(0xde), &syscall)
+            .await
+            .unwrap();
+
+        let block = serde_json::json!({ "blockHash": latest(1_000).hash.unwrap() });
+        mock.assert_request("eth_chainId", ()).unwrap();
+        mock.assert_request("eth_getBlockByNumber", ("latest", false)).unwrap();
+        mock.assert_request("eth_getStorageAt", (Address::repeat_byte(0xde), U256::zero(), block))
+            .unwrap();
+        assert_eq!(report.threshold(), 2);
+        assert!(report.is_id_match());
+        assert!(!report.is_deadline_expired());
+        assert_eq!(report.signers().len(), 3);
+        assert!(report.signers()[0].is_valid());
+        assert!(!report.signers()[1].is_authorized());
+        assert!(report.signers()[2].signer().is_none());
+        assert!(!report.is_valid());
+    }
+
+    #[tokio::test]
+    async fn test_verify_syscall_live() {
+        let report = verify_one(signature(1_002).await, 1_002).await;
+
+        assert!(report.signers()[0].is_valid());
+        assert!(report.is_valid());
+    }
+
+    #[tokio::test]
+    async fn test_verify_syscall_unrecoverable_v() {
+        let signed = signature(2_000).await;
+        let signature = DeckSignature::new(signed.v() - 27, signed.r(), signed.s());
+        let report = verify_one(signature, 2_000).await;
+
+        assert!(report.signers()[0].signer().is_none());
+        assert!(!report.signers()[0].is_valid());
+        assert!(!report.is_valid());
+    }
+
+    #[tokio::test]
+    async fn test_verify_syscall_deadline_next_block() {
+        // the next block's timestamp is at least 1_001, where `1_001 > 1_001` fails
+        let report = verify_one(signature(1_001).await, 1_001).await;
+
+        assert!(report.is_deadline_expired());
+        assert!(!report.is_valid());
+    }
+}